@@ -13,8 +13,10 @@ fn main() {
     // Записываем его в нужную директорию
     header.write_to_file(&header_path);
 
-    println!("cargo:rerun-if-changed=src/lib.rs");
+    // The ABI version and the FFI types live all over `src`, the header must follow them.
+    println!("cargo:rerun-if-changed=src");
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=wrapper.h");
 
     println!("Generated header: {:?}", header_path);
 
@@ -36,8 +38,11 @@ fn main() {
         // Unwrap the Result and panic on failure.
         .expect("Unable to generate bindings");
 
-    // Write the bindings to the $OUT_DIR/bindings.rs file.
-    bindings
-        .write_to_file("src/bindings.rs")
-        .expect("Couldn't write bindings!");
+    // Write the bindings to src/bindings.rs, only when they changed: a fresh mtime
+    // inside `src` would make the script rerun on every build.
+    let bindings_path = "src/bindings.rs";
+    let bindings = bindings.to_string();
+    if std::fs::read_to_string(bindings_path).ok().as_deref() != Some(bindings.as_str()) {
+        std::fs::write(bindings_path, bindings).expect("Couldn't write bindings!");
+    }
 }
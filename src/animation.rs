@@ -0,0 +1,122 @@
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    defold::{AnimationId, MessagePayload, URL},
+    defold_cpp_interface::dmhash_t,
+    defold_warn,
+    messages::{DefoldInboundSet, DefoldMessage, DefoldMessageReceived, try_decode},
+};
+
+/// Sent once a `ONCE_*` animation started with
+/// [`animate_property`](crate::defold::animate_property) completes. Match `url`
+/// against a view with [`URL::same_object`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct AnimationFinished {
+    pub url: URL,
+    pub property: dmhash_t,
+    pub id: AnimationId,
+}
+
+/// Completion message C++ sends through `app_on_message`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct AnimationFinishedMessage {
+    id: u64,
+    property: dmhash_t,
+}
+
+impl MessagePayload for AnimationFinishedMessage {}
+
+impl DefoldMessage for AnimationFinishedMessage {
+    const NAME: &'static str = "animation_finished";
+}
+
+fn emit_finished_animations(
+    mut messages: EventReader<DefoldMessageReceived>,
+    mut finished: EventWriter<AnimationFinished>,
+) {
+    for message in messages.read() {
+        match try_decode::<AnimationFinishedMessage>(message) {
+            Some(Ok(AnimationFinishedMessage { id, property })) => {
+                finished.send(AnimationFinished {
+                    url: message.sender,
+                    property,
+                    id: AnimationId(id),
+                });
+            }
+            Some(Err(error)) => defold_warn!("invalid animation_finished payload: {}", error),
+            None => {}
+        }
+    }
+}
+
+pub struct AnimationPlugin;
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnimationFinished>()
+            .add_systems(PreUpdate, emit_finished_animations.after(DefoldInboundSet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use bevy_app::Update;
+    use no_std_strings::ztr64;
+
+    use super::*;
+    use crate::{
+        defold_cpp_interface::HashedUrl,
+        dmhash,
+        messages::{DefoldMessagesPlugin, queue_defold_message},
+    };
+
+    #[derive(Resource, Default)]
+    struct Finished(Vec<AnimationFinished>);
+
+    #[test]
+    fn test_completion_message_becomes_event() {
+        let mut app = App::new();
+        app.add_plugins((DefoldMessagesPlugin, AnimationPlugin))
+            .init_resource::<Finished>()
+            .add_systems(
+                Update,
+                |mut events: EventReader<AnimationFinished>, mut finished: ResMut<Finished>| {
+                    finished.0.extend(events.read().copied());
+                },
+            );
+        let view_url = URL::with_hashed(
+            ztr64::create("main:/popup"),
+            HashedUrl {
+                socket: dmhash!("main"),
+                path: dmhash!("/popup"),
+                fragment: 0,
+            },
+        );
+        let sender = URL::with_hashed(ztr64::create("/popup"), view_url.hashed().unwrap());
+        let property = dmhash!("scale");
+
+        queue_defold_message(
+            app.world_mut(),
+            DefoldMessageReceived {
+                sender,
+                id: dmhash!("animation_finished"),
+                payload: alloc::format!(r#"{{"id":3,"property":{}}}"#, property).into_bytes(),
+            },
+        );
+        app.update();
+
+        let finished = &app.world().resource::<Finished>().0;
+        assert_eq!(
+            *finished,
+            [AnimationFinished {
+                url: sender,
+                property,
+                id: AnimationId(3),
+            }]
+        );
+        assert!(finished[0].url.same_object(&view_url));
+    }
+}
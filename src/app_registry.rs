@@ -0,0 +1,300 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use bevy_app::App;
+use spin::Mutex;
+
+/// Opaque id of an `App` owned by Rust.
+///
+/// The low 32 bits store the slot index plus one, the high 32 bits store the slot
+/// generation, so `0` is never a valid handle and a destroyed handle is never reused.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AppHandle(u64);
+
+impl AppHandle {
+    pub const NULL: Self = AppHandle(0);
+
+    const fn new(index: u32, generation: u32) -> Self {
+        Self(((generation as u64) << 32) | (index as u64 + 1))
+    }
+
+    pub const fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+
+    const fn index(&self) -> usize {
+        (self.0 as u32 - 1) as usize
+    }
+
+    const fn generation(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+}
+
+/// Result of every FFI call that receives an [`AppHandle`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppStatus {
+    Ok,
+    /// The host passed `0` as a handle.
+    NullHandle,
+    /// The handle was never issued by this library.
+    InvalidHandle,
+    /// The app behind the handle was already destroyed.
+    StaleHandle,
+    /// The app is in the middle of another call (reentrant call from a system).
+    AppBusy,
+    /// The entry point does not match the [`TimeMode`](crate::host_time::TimeMode) of the app.
+    WrongTimeMode,
+    /// An argument is out of range (negative or non finite time step, etc).
+    InvalidArgument,
+    /// The output buffer is smaller than the length written to `out_len`.
+    BufferTooSmall,
+    /// The snapshot blob is corrupted, from another version or for other plugins.
+    InvalidSnapshot,
+    /// `app_step` was called on an app that is not paused.
+    NotPaused,
+    /// No handler is registered for the method passed to `app_call`.
+    UnknownMethod,
+    /// The JSON passed by the host could not be parsed into the expected type.
+    InvalidJson,
+    /// An `app_call` handler could not run or its reply is too large.
+    HandlerFailed,
+    /// The reply of an `app_call` handler can't be written as JSON.
+    InvalidReply,
+}
+
+impl AppStatus {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            AppStatus::Ok => "ok",
+            AppStatus::NullHandle => "null app handle",
+            AppStatus::InvalidHandle => "invalid app handle",
+            AppStatus::StaleHandle => "app already destroyed",
+            AppStatus::AppBusy => "app is busy",
+            AppStatus::WrongTimeMode => "call does not match app time mode",
+            AppStatus::InvalidArgument => "invalid argument",
+            AppStatus::BufferTooSmall => "output buffer too small",
+            AppStatus::InvalidSnapshot => "invalid snapshot",
+            AppStatus::NotPaused => "app is not paused",
+            AppStatus::UnknownMethod => "unknown method",
+            AppStatus::InvalidJson => "invalid json",
+            AppStatus::HandlerFailed => "handler failed",
+            AppStatus::InvalidReply => "handler reply is not valid json",
+        }
+    }
+}
+
+enum Slot {
+    Vacant { generation: u32 },
+    Occupied { generation: u32, app: Box<App> },
+    InUse { generation: u32 },
+}
+
+impl Slot {
+    const fn generation(&self) -> u32 {
+        match self {
+            Slot::Vacant { generation }
+            | Slot::Occupied { generation, .. }
+            | Slot::InUse { generation } => *generation,
+        }
+    }
+}
+
+pub(crate) struct AppRegistry {
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+}
+
+// SAFETY: apps are created, updated and destroyed only from the Defold script thread,
+// the mutex around the registry only guards the bookkeeping itself.
+unsafe impl Send for AppRegistry {}
+
+impl AppRegistry {
+    pub(crate) const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, app: Box<App>) -> AppHandle {
+        if let Some(index) = self.free_slots.pop() {
+            let slot = &mut self.slots[index as usize];
+            let generation = slot.generation();
+            *slot = Slot::Occupied { generation, app };
+            return AppHandle::new(index, generation);
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(Slot::Occupied { generation: 0, app });
+        AppHandle::new(index, 0)
+    }
+
+    fn validate(&self, handle: AppHandle) -> Result<(), AppStatus> {
+        if handle.is_null() {
+            return Err(AppStatus::NullHandle);
+        }
+        // Never issued: the index part is stored plus one, `index` would underflow.
+        if handle.0 as u32 == 0 {
+            return Err(AppStatus::InvalidHandle);
+        }
+        let Some(slot) = self.slots.get(handle.index()) else {
+            return Err(AppStatus::InvalidHandle);
+        };
+        if slot.generation() != handle.generation() {
+            return Err(if slot.generation() > handle.generation() {
+                AppStatus::StaleHandle
+            } else {
+                AppStatus::InvalidHandle
+            });
+        }
+        match slot {
+            Slot::Vacant { .. } => Err(AppStatus::StaleHandle),
+            Slot::InUse { .. } => Err(AppStatus::AppBusy),
+            Slot::Occupied { .. } => Ok(()),
+        }
+    }
+
+    /// Moves the app out of its slot for the duration of a call, so a reentrant call
+    /// with the same handle gets [`AppStatus::AppBusy`] instead of a second `&mut App`.
+    pub(crate) fn checkout(&mut self, handle: AppHandle) -> Result<Box<App>, AppStatus> {
+        self.validate(handle)?;
+        let slot = &mut self.slots[handle.index()];
+        let generation = slot.generation();
+        match core::mem::replace(slot, Slot::InUse { generation }) {
+            Slot::Occupied { app, .. } => Ok(app),
+            _ => unreachable!("slot state checked by validate"),
+        }
+    }
+
+    pub(crate) fn checkin(&mut self, handle: AppHandle, app: Box<App>) {
+        let slot = &mut self.slots[handle.index()];
+        debug_assert!(
+            matches!(slot, Slot::InUse { generation } if *generation == handle.generation())
+        );
+        *slot = Slot::Occupied {
+            generation: handle.generation(),
+            app,
+        };
+    }
+
+    pub(crate) fn remove(&mut self, handle: AppHandle) -> Result<Box<App>, AppStatus> {
+        self.validate(handle)?;
+        let index = handle.index();
+        let next_generation = handle.generation().wrapping_add(1);
+        let removed = core::mem::replace(
+            &mut self.slots[index],
+            Slot::Vacant {
+                generation: next_generation,
+            },
+        );
+        // Once the generation wraps around an old handle could become valid again, retire the slot instead.
+        if next_generation != 0 {
+            self.free_slots.push(index as u32);
+        }
+        match removed {
+            Slot::Occupied { app, .. } => Ok(app),
+            _ => unreachable!("slot state checked by validate"),
+        }
+    }
+}
+
+static APP_REGISTRY: Mutex<AppRegistry> = Mutex::new(AppRegistry::new());
+
+pub(crate) fn register_app(app: App) -> AppHandle {
+    APP_REGISTRY.lock().insert(Box::new(app))
+}
+
+/// Runs `f` with the app behind `handle`. The registry lock is not held while `f` runs.
+pub(crate) fn with_app<R>(
+    handle: AppHandle,
+    f: impl FnOnce(&mut App) -> R,
+) -> Result<R, AppStatus> {
+    let mut app = APP_REGISTRY.lock().checkout(handle)?;
+    let result = f(&mut app);
+    APP_REGISTRY.lock().checkin(handle, app);
+    Ok(result)
+}
+
+pub(crate) fn unregister_app(handle: AppHandle) -> Result<Box<App>, AppStatus> {
+    APP_REGISTRY.lock().remove(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_returns_non_null_handle() {
+        let mut registry = AppRegistry::new();
+        let handle = registry.insert(Box::new(App::new()));
+        assert!(!handle.is_null());
+    }
+
+    #[test]
+    fn test_null_handle_rejected() {
+        let mut registry = AppRegistry::new();
+        assert_eq!(
+            registry.checkout(AppHandle::NULL).err(),
+            Some(AppStatus::NullHandle)
+        );
+        assert_eq!(
+            registry.remove(AppHandle::NULL).err(),
+            Some(AppStatus::NullHandle)
+        );
+    }
+
+    #[test]
+    fn test_unknown_handle_rejected() {
+        let mut registry = AppRegistry::new();
+        assert_eq!(
+            registry.checkout(AppHandle::new(3, 0)).err(),
+            Some(AppStatus::InvalidHandle)
+        );
+        assert_eq!(
+            registry.checkout(AppHandle(1 << 32)).err(),
+            Some(AppStatus::InvalidHandle)
+        );
+        assert_eq!(
+            registry.remove(AppHandle(1 << 32)).err(),
+            Some(AppStatus::InvalidHandle)
+        );
+    }
+
+    #[test]
+    fn test_double_remove_is_stale() {
+        let mut registry = AppRegistry::new();
+        let handle = registry.insert(Box::new(App::new()));
+        assert!(registry.remove(handle).is_ok());
+        assert_eq!(registry.remove(handle).err(), Some(AppStatus::StaleHandle));
+    }
+
+    #[test]
+    fn test_use_after_remove_is_stale_even_if_slot_reused() {
+        let mut registry = AppRegistry::new();
+        let old_handle = registry.insert(Box::new(App::new()));
+        registry.remove(old_handle).unwrap();
+        let new_handle = registry.insert(Box::new(App::new()));
+
+        assert_ne!(old_handle, new_handle);
+        assert_eq!(
+            registry.checkout(old_handle).err(),
+            Some(AppStatus::StaleHandle)
+        );
+        assert!(registry.checkout(new_handle).is_ok());
+    }
+
+    #[test]
+    fn test_checked_out_app_is_busy() {
+        let mut registry = AppRegistry::new();
+        let handle = registry.insert(Box::new(App::new()));
+        let app = registry.checkout(handle).unwrap();
+
+        assert_eq!(registry.checkout(handle).err(), Some(AppStatus::AppBusy));
+        assert_eq!(registry.remove(handle).err(), Some(AppStatus::AppBusy));
+
+        registry.checkin(handle, app);
+        assert!(registry.remove(handle).is_ok());
+    }
+}
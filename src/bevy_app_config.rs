@@ -1,15 +1,321 @@
+use alloc::{collections::BTreeMap, string::String};
+
 use bevy_app::{App, Update};
 use bevy_time::TimePlugin;
 use no_std_strings::ztr64;
+use serde::{Deserialize, Deserializer, de::Visitor};
 
-use crate::defold;
+use crate::{
+    animation::AnimationPlugin,
+    command_buffer::DefoldCommandBufferPlugin,
+    defold::{self, LogLevel},
+    host_time::{TimeMode, configure_time},
+    input::DefoldInputPlugin,
+    messages::DefoldMessagesPlugin,
+    rng::GameRng,
+    rpc::RpcPlugin,
+    simulation::SimulationControlPlugin,
+    transform_sync::DefoldSyncPlugin,
+    view::DefoldViewPlugin,
+};
 
 fn test_log() {
     defold::log_info(ztr64::create("update triggered"));
 }
 
-pub(crate) fn get_app() -> App {
+/// Plugins every app needs to talk to Defold, whatever the configuration.
+fn get_base_app(time_mode: TimeMode) -> App {
     let mut app = App::new();
-    app.add_plugins(TimePlugin).add_systems(Update, test_log);
+    app.add_plugins((
+        TimePlugin,
+        DefoldMessagesPlugin,
+        DefoldInputPlugin,
+        DefoldCommandBufferPlugin,
+        DefoldViewPlugin,
+        DefoldSyncPlugin,
+        SimulationControlPlugin,
+        RpcPlugin,
+        AnimationPlugin,
+    ));
+    #[cfg(feature = "snapshot")]
+    app.add_plugins(crate::snapshot::SnapshotPlugin);
+    configure_time(&mut app, time_mode);
+    app
+}
+
+pub(crate) fn get_app(time_mode: TimeMode) -> App {
+    let mut app = get_base_app(time_mode);
+    app.add_systems(Update, test_log);
     app
 }
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub(crate) struct BoardConfig {
+    root: [f32; 2],
+    offset: [f32; 2],
+    bounds_min: [i32; 2],
+    bounds_max: [i32; 2],
+}
+
+/// Target of a `log_targets` entry. serde-json-core only reads map keys as
+/// borrowed strings, so the key is copied out of one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct LogTarget(String);
+
+impl<'de> Deserialize<'de> for LogTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LogTargetVisitor;
+
+        impl Visitor<'_> for LogTargetVisitor {
+            type Value = LogTarget;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a log target")
+            }
+
+            fn visit_str<E>(self, target: &str) -> Result<LogTarget, E> {
+                Ok(LogTarget(target.into()))
+            }
+        }
+
+        deserializer.deserialize_str(LogTargetVisitor)
+    }
+}
+
+/// World description passed by the host to `create_world_with_config`, e.g.
+/// `{"time_mode":"host_driven","particles":true,"rng_seed":7,"board":{"root":[0,0],"offset":[64,64],"bounds_min":[0,0],"bounds_max":[7,7]}}`.
+/// Every field is optional.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct WorldConfig {
+    time_mode: TimeMode,
+    particles: bool,
+    board: Option<BoardConfig>,
+    rng_seed: u64,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            time_mode: TimeMode::WallClock,
+            particles: false,
+            board: None,
+            rng_seed: 0,
+        }
+    }
+}
+
+/// Settings shared by every world of the process, passed by the host to
+/// `configure_library` once before creating worlds, e.g.
+/// `{"log_level":"error","log_targets":{"bevy_ecs":"warn"},"max_payload_len":65536}`.
+/// Every field is optional.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct LibraryConfig {
+    log_level: LogLevel,
+    /// Minimum level of `log` records per target, e.g. `{"bevy_ecs": "warn"}`.
+    log_targets: BTreeMap<LogTarget, LogLevel>,
+    max_payload_len: usize,
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self {
+            log_level: LogLevel::Info,
+            log_targets: BTreeMap::new(),
+            max_payload_len: defold::DEFAULT_MAX_PAYLOAD_LEN,
+        }
+    }
+}
+
+pub(crate) fn parse_library_config(
+    json: &[u8],
+) -> Result<LibraryConfig, serde_json_core::de::Error> {
+    serde_json_core::from_slice::<LibraryConfig>(json).map(|(config, _)| config)
+}
+
+pub(crate) fn apply_library_config(config: &LibraryConfig) {
+    defold::set_min_log_level(config.log_level);
+    defold::set_log_target_filters(
+        config
+            .log_targets
+            .iter()
+            .map(|(LogTarget(target), level)| (target.clone(), *level))
+            .collect(),
+    );
+    defold::set_max_payload_len(config.max_payload_len);
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum WorldConfigError {
+    InvalidJson(serde_json_core::de::Error),
+    InvalidBoard,
+    /// The config asks for a subsystem this build was compiled without.
+    #[cfg_attr(all(feature = "board", feature = "particles"), allow(dead_code))]
+    NotCompiledIn,
+}
+
+pub(crate) fn parse_world_config(json: &[u8]) -> Result<WorldConfig, WorldConfigError> {
+    serde_json_core::from_slice::<WorldConfig>(json)
+        .map(|(config, _)| config)
+        .map_err(WorldConfigError::InvalidJson)
+}
+
+#[cfg(feature = "board")]
+fn insert_board(app: &mut App, board_config: &BoardConfig) -> Result<(), WorldConfigError> {
+    use bevy_math::{IVec2, Vec2};
+
+    use crate::{board::Board, graph::Graph};
+
+    let board = Board::new(
+        Vec2::from_array(board_config.root),
+        Vec2::from_array(board_config.offset),
+        IVec2::from_array(board_config.bounds_min),
+        IVec2::from_array(board_config.bounds_max),
+        Graph::new(),
+    )
+    .ok_or(WorldConfigError::InvalidBoard)?;
+    app.insert_resource(board);
+    Ok(())
+}
+
+#[cfg(not(feature = "board"))]
+fn insert_board(_app: &mut App, _board_config: &BoardConfig) -> Result<(), WorldConfigError> {
+    Err(WorldConfigError::NotCompiledIn)
+}
+
+#[cfg(feature = "particles")]
+fn add_particles(app: &mut App) -> Result<(), WorldConfigError> {
+    app.add_plugins(crate::particles::ParticlesPlugin);
+    Ok(())
+}
+
+#[cfg(not(feature = "particles"))]
+fn add_particles(_app: &mut App) -> Result<(), WorldConfigError> {
+    Err(WorldConfigError::NotCompiledIn)
+}
+
+pub(crate) fn get_app_from_config(config: &WorldConfig) -> Result<App, WorldConfigError> {
+    let mut app = get_base_app(config.time_mode);
+
+    if let Some(board_config) = &config.board {
+        insert_board(&mut app, board_config)?;
+    }
+
+    if config.particles {
+        add_particles(&mut app)?;
+    }
+
+    app.insert_resource(GameRng::new(config.rng_seed));
+    Ok(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty_config_uses_defaults() {
+        assert_eq!(parse_world_config(b"{}"), Ok(WorldConfig::default()));
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let json = br#"{
+            "time_mode": "host_driven",
+            "particles": true,
+            "board": {"root": [1.5, 2], "offset": [64, 32], "bounds_min": [0, 0], "bounds_max": [7, 9]},
+            "rng_seed": 7
+        }"#;
+
+        let config = parse_world_config(json).unwrap();
+
+        assert_eq!(config.time_mode, TimeMode::HostDriven);
+        assert!(config.particles);
+        assert_eq!(
+            config.board,
+            Some(BoardConfig {
+                root: [1.5, 2.0],
+                offset: [64.0, 32.0],
+                bounds_min: [0, 0],
+                bounds_max: [7, 9],
+            })
+        );
+        assert_eq!(config.rng_seed, 7);
+    }
+
+    #[test]
+    fn test_parse_library_config() {
+        assert_eq!(parse_library_config(b"{}"), Ok(LibraryConfig::default()));
+
+        let config = parse_library_config(
+            br#"{
+                "log_level": "error",
+                "log_targets": {"bevy_ecs": "warn", "rust_defold_try::board": "debug"},
+                "max_payload_len": 4096
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.log_level, LogLevel::Error);
+        assert_eq!(
+            config.log_targets,
+            BTreeMap::from([
+                (LogTarget("bevy_ecs".into()), LogLevel::Warn),
+                (LogTarget("rust_defold_try::board".into()), LogLevel::Debug),
+            ])
+        );
+        assert_eq!(config.max_payload_len, 4096);
+    }
+
+    #[test]
+    fn test_parse_invalid_json() {
+        assert!(matches!(
+            parse_world_config(br#"{"particles": tru}"#),
+            Err(WorldConfigError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            parse_world_config(br#"{"time_mode": "sometimes"}"#),
+            Err(WorldConfigError::InvalidJson(_))
+        ));
+    }
+
+    #[cfg(feature = "board")]
+    #[test]
+    fn test_app_from_config_with_board_and_seed() {
+        let config = parse_world_config(
+            br#"{"rng_seed": 3, "board": {"root": [0, 0], "offset": [1, 1], "bounds_min": [0, 0], "bounds_max": [4, 4]}}"#,
+        )
+        .unwrap();
+
+        let app = get_app_from_config(&config).unwrap();
+
+        assert!(app.world().contains_resource::<crate::board::Board>());
+        assert_eq!(app.world().resource::<GameRng>(), &GameRng::new(3));
+    }
+
+    #[cfg(feature = "board")]
+    #[test]
+    fn test_app_from_config_invalid_board() {
+        let config = parse_world_config(
+            br#"{"board": {"root": [0, 0], "offset": [0, 1], "bounds_min": [0, 0], "bounds_max": [4, 4]}}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            get_app_from_config(&config),
+            Err(WorldConfigError::InvalidBoard)
+        ));
+    }
+
+    #[cfg(not(feature = "particles"))]
+    #[test]
+    fn test_app_from_config_missing_subsystem() {
+        let config = parse_world_config(br#"{"particles": true}"#).unwrap();
+
+        assert!(matches!(
+            get_app_from_config(&config),
+            Err(WorldConfigError::NotCompiledIn)
+        ));
+    }
+}
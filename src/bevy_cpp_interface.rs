@@ -1,25 +1,503 @@
-use alloc::boxed::Box;
-use core::mem;
+use core::{ffi::CStr, fmt::Write, slice::from_raw_parts};
 
-use bevy_app::App;
+use alloc::vec::Vec;
+use bevy_math::Vec2;
 
-use crate::bevy_app_config::get_app;
+use no_std_strings::ztr64;
 
+use crate::{
+    app_registry::{AppHandle, AppStatus, register_app, unregister_app, with_app},
+    bevy_app_config::{
+        WorldConfigError, apply_library_config, get_app, get_app_from_config, parse_library_config,
+        parse_world_config,
+    },
+    command_buffer::{report_buffered_view, take_defold_commands},
+    defold::{self, URL},
+    defold_cpp_interface::{CreateViewResultCpp, DefoldCommandCpp, HashedUrl, dmhash_t},
+    frame_phases::{
+        claim_whole_frame_driver, run_fixed_update_phase, run_late_update_phase, run_update_phase,
+    },
+    host_time::{TimeMode, expect_time_mode, set_host_delta},
+    input::{DefoldInputAction, apply_defold_input},
+    messages::{DefoldMessageReceived, queue_defold_message},
+    rpc::{RpcError, call_rpc},
+    simulation::{pause_simulation, resume_simulation, set_time_scale, step_simulation},
+    view::delete_live_views,
+};
+
+fn report_status(entry_point: &str, status: AppStatus) -> AppStatus {
+    if status != AppStatus::Ok {
+        let mut message = ztr64::new();
+        let _ = write!(message, "{}: {}", entry_point, status.description());
+        defold::log_error(message);
+    }
+    status
+}
+
+#[cfg(feature = "snapshot")]
+fn snapshot_status(entry_point: &str, error: crate::snapshot::SnapshotError) -> AppStatus {
+    let mut message = ztr64::new();
+    let _ = write!(message, "{}: {:?}", entry_point, error);
+    defold::log_error(message);
+    AppStatus::InvalidSnapshot
+}
+
+fn status_of<R>(entry_point: &str, result: Result<R, AppStatus>) -> AppStatus {
+    report_status(entry_point, result.err().unwrap_or(AppStatus::Ok))
+}
+
+/// Bumped on every change of the exported functions or `#[repr(C)]` types.
+pub const RUST_DEFOLD_ABI_VERSION: u32 = 12;
+
+pub const RUST_DEFOLD_CAPABILITY_PARTICLES: u32 = 1 << 0;
+pub const RUST_DEFOLD_CAPABILITY_BOARD: u32 = 1 << 1;
+pub const RUST_DEFOLD_CAPABILITY_GRAPH: u32 = 1 << 2;
+pub const RUST_DEFOLD_CAPABILITY_SNAPSHOT: u32 = 1 << 3;
+
+/// ABI version this library was built with, the host compares it with the
+/// `RUST_DEFOLD_ABI_VERSION` of the header it was compiled against.
 #[unsafe(no_mangle)]
-pub extern "C" fn create_and_init_world() -> *mut App {
-    let mut app_boxed = Box::new(get_app());
-    let app_ptr = app_boxed.as_mut() as *mut App;
-    mem::forget(app_boxed); // prevent the Box from being dropped
-    app_ptr
+pub extern "C" fn rust_defold_abi_version() -> u32 {
+    RUST_DEFOLD_ABI_VERSION
 }
 
+/// Bitmask of the `RUST_DEFOLD_CAPABILITY_*` subsystems compiled into this library.
 #[unsafe(no_mangle)]
-pub extern "C" fn update_app(app: *mut App) {
-    let app = unsafe { &mut *app };
-    app.update();
+pub extern "C" fn rust_defold_capabilities() -> u32 {
+    let mut capabilities = 0;
+    if cfg!(feature = "particles") {
+        capabilities |= RUST_DEFOLD_CAPABILITY_PARTICLES;
+    }
+    if cfg!(feature = "board") {
+        capabilities |= RUST_DEFOLD_CAPABILITY_BOARD;
+    }
+    if cfg!(feature = "graph") {
+        capabilities |= RUST_DEFOLD_CAPABILITY_GRAPH;
+    }
+    if cfg!(feature = "snapshot") {
+        capabilities |= RUST_DEFOLD_CAPABILITY_SNAPSHOT;
+    }
+    capabilities
+}
+
+fn is_abi_compatible(entry_point: &str, host_abi_version: u32) -> bool {
+    if host_abi_version == RUST_DEFOLD_ABI_VERSION {
+        return true;
+    }
+    let mut message = ztr64::new();
+    let _ = write!(
+        message,
+        "{}: host abi {} != library abi {}",
+        entry_point, host_abi_version, RUST_DEFOLD_ABI_VERSION
+    );
+    defold::log_error(message);
+    false
+}
+
+/// Applies a JSON [`LibraryConfig`](crate::bevy_app_config::LibraryConfig) to the
+/// whole library, so every live and future world shares it. Also installs the
+/// `log` backend, call it before creating worlds.
+///
+/// # Safety
+/// `json_ptr` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn configure_library(json_ptr: *const u8, len: usize) -> AppStatus {
+    defold::install_logger();
+    if json_ptr.is_null() {
+        return report_status("configure_library", AppStatus::InvalidArgument);
+    }
+    let json = unsafe { from_raw_parts(json_ptr, len) };
+    match parse_library_config(json) {
+        Ok(config) => {
+            apply_library_config(&config);
+            AppStatus::Ok
+        }
+        Err(error) => {
+            let mut message = ztr64::new();
+            let _ = write!(message, "configure_library: {}", error);
+            defold::log_error(message);
+            AppStatus::InvalidJson
+        }
+    }
+}
+
+/// Returns [`AppHandle::NULL`] when `host_abi_version` does not match [`RUST_DEFOLD_ABI_VERSION`].
+/// Also installs the `log` backend writing to the Defold console.
+#[unsafe(no_mangle)]
+pub extern "C" fn create_and_init_world(host_abi_version: u32, time_mode: TimeMode) -> AppHandle {
+    defold::install_logger();
+    if !is_abi_compatible("create_and_init_world", host_abi_version) {
+        return AppHandle::NULL;
+    }
+    register_app(get_app(time_mode))
+}
+
+#[repr(C)]
+pub enum CreateWorldResult {
+    Success {
+        app: AppHandle,
+    },
+    NullConfig,
+    InvalidJson,
+    InvalidBoard,
+    IncompatibleAbi,
+    /// The config asks for a subsystem missing from [`rust_defold_capabilities`].
+    NotCompiledIn,
 }
 
+/// Builds an app from a JSON [`WorldConfig`](crate::bevy_app_config::WorldConfig),
+/// installing the `log` backend like [`create_and_init_world`].
+///
+/// # Safety
+/// `json_ptr` must point to `len` readable bytes.
 #[unsafe(no_mangle)]
-pub extern "C" fn destroy_app(app: *mut App) {
-    let _ = unsafe { Box::from_raw(app) };
+pub unsafe extern "C" fn create_world_with_config(
+    host_abi_version: u32,
+    json_ptr: *const u8,
+    len: usize,
+) -> CreateWorldResult {
+    defold::install_logger();
+    if !is_abi_compatible("create_world_with_config", host_abi_version) {
+        return CreateWorldResult::IncompatibleAbi;
+    }
+    if json_ptr.is_null() {
+        defold::log_error(ztr64::create("create_world_with_config: null config"));
+        return CreateWorldResult::NullConfig;
+    }
+    let json = unsafe { from_raw_parts(json_ptr, len) };
+
+    match parse_world_config(json).and_then(|config| get_app_from_config(&config)) {
+        Ok(app) => CreateWorldResult::Success {
+            app: register_app(app),
+        },
+        Err(WorldConfigError::InvalidJson(error)) => {
+            let mut message = ztr64::new();
+            let _ = write!(message, "create_world_with_config: {}", error);
+            defold::log_error(message);
+            CreateWorldResult::InvalidJson
+        }
+        Err(WorldConfigError::InvalidBoard) => {
+            defold::log_error(ztr64::create("create_world_with_config: invalid board"));
+            CreateWorldResult::InvalidBoard
+        }
+        Err(WorldConfigError::NotCompiledIn) => {
+            defold::log_error(ztr64::create(
+                "create_world_with_config: subsystem not compiled in",
+            ));
+            CreateWorldResult::NotCompiledIn
+        }
+    }
+}
+
+/// Advances an app created with [`TimeMode::WallClock`].
+#[unsafe(no_mangle)]
+pub extern "C" fn update_app(app: AppHandle) -> AppStatus {
+    let result = with_app(app, |app| {
+        expect_time_mode(app.world(), TimeMode::WallClock)?;
+        app.update();
+        Ok(())
+    });
+    status_of("update_app", result.and_then(|result| result))
+}
+
+/// Advances an app created with [`TimeMode::HostDriven`] by the `dt` Defold passed into `update()`.
+/// Can not be combined with the phase entry points below on the same app.
+#[unsafe(no_mangle)]
+pub extern "C" fn update_app_with_dt(app: AppHandle, dt_seconds: f32) -> AppStatus {
+    let result = with_app(app, |app| {
+        claim_whole_frame_driver(app.world_mut())?;
+        set_host_delta(app.world_mut(), dt_seconds)?;
+        app.update();
+        Ok(())
+    });
+    status_of("update_app_with_dt", result.and_then(|result| result))
+}
+
+/// Deletes the game objects the app created, see [`LiveViews`](crate::view::LiveViews).
+#[unsafe(no_mangle)]
+pub extern "C" fn destroy_app(app: AppHandle) -> AppStatus {
+    let result = unregister_app(app).map(|mut app| delete_live_views(app.world_mut()));
+    status_of("destroy_app", result)
+}
+
+/// Runs the fixed schedules of a [`TimeMode::HostDriven`] app from Defold `fixed_update`.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_fixed_update(app: AppHandle, fixed_dt_seconds: f32) -> AppStatus {
+    let result = with_app(app, |app| {
+        run_fixed_update_phase(app.world_mut(), fixed_dt_seconds)
+    });
+    status_of("app_fixed_update", result.and_then(|result| result))
+}
+
+/// Runs the schedules up to `Update` of a [`TimeMode::HostDriven`] app from Defold `update`.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_update(app: AppHandle, dt_seconds: f32) -> AppStatus {
+    let result = with_app(app, |app| run_update_phase(app.world_mut(), dt_seconds));
+    status_of("app_update", result.and_then(|result| result))
+}
+
+/// Runs `PostUpdate` and the end of the frame of a [`TimeMode::HostDriven`] app from Defold `late_update`.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_late_update(app: AppHandle) -> AppStatus {
+    let result = with_app(app, |app| run_late_update_phase(app.world_mut()));
+    status_of("app_late_update", result.and_then(|result| result))
+}
+
+/// Queues a message from a Defold `on_message` callback, it is emitted as a
+/// [`DefoldMessageReceived`] event during the next update of the app. The sender
+/// keeps `sender_hashed`, so it compares equal to the URLs of created views.
+///
+/// # Safety
+/// `sender_url` must be a null terminated string, `json_ptr` must point to `len`
+/// readable bytes or be null when `len` is `0`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_on_message(
+    app: AppHandle,
+    sender_url: *const cty::c_char,
+    sender_hashed: HashedUrl,
+    message_id: dmhash_t,
+    json_ptr: *const u8,
+    len: usize,
+) -> AppStatus {
+    if sender_url.is_null() || (json_ptr.is_null() && len != 0) {
+        return report_status("app_on_message", AppStatus::InvalidArgument);
+    }
+    let Ok(sender) = URL::from_engine(
+        unsafe { CStr::from_ptr(sender_url) }.to_bytes(),
+        sender_hashed,
+    ) else {
+        return report_status("app_on_message", AppStatus::InvalidArgument);
+    };
+    let payload = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { from_raw_parts(json_ptr, len) }.to_vec()
+    };
+
+    let result = with_app(app, |app| {
+        queue_defold_message(
+            app.world_mut(),
+            DefoldMessageReceived {
+                sender,
+                id: message_id,
+                payload,
+            },
+        )
+    });
+    status_of("app_on_message", result)
+}
+
+/// Forwards one Defold `on_input` call into the [`DefoldInput`](crate::input::DefoldInput)
+/// resource. `action_id` is `0` for pointer movement.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn app_on_input(
+    app: AppHandle,
+    action_id: dmhash_t,
+    pressed: bool,
+    released: bool,
+    value: f32,
+    x: f32,
+    y: f32,
+    screen_x: f32,
+    screen_y: f32,
+) -> AppStatus {
+    let action = DefoldInputAction {
+        action_id,
+        pressed,
+        released,
+        value,
+        position: Vec2::new(x, y),
+        screen_position: Vec2::new(screen_x, screen_y),
+    };
+    let result = with_app(app, |app| apply_defold_input(app.world_mut(), action));
+    status_of("app_on_input", result)
+}
+
+/// Hands the commands queued in [`DefoldCommandBuffer`](crate::command_buffer::DefoldCommandBuffer)
+/// since the previous call to C++. The records stay valid until the next call or `destroy_app`.
+///
+/// # Safety
+/// `out_commands` and `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_take_commands(
+    app: AppHandle,
+    out_commands: *mut *const DefoldCommandCpp,
+    out_len: *mut usize,
+) -> AppStatus {
+    if out_commands.is_null() || out_len.is_null() {
+        return report_status("app_take_commands", AppStatus::InvalidArgument);
+    }
+    let result = with_app(app, |app| {
+        let commands = take_defold_commands(app.world_mut());
+        unsafe {
+            *out_commands = commands.as_ptr();
+            *out_len = commands.len();
+        }
+    });
+    status_of("app_take_commands", result)
+}
+
+/// Reports the outcome of a `CreateView` record handed out by `app_take_commands`.
+/// Created views are registered for deletion with the app and announced as
+/// [`BufferedViewCreated`](crate::command_buffer::BufferedViewCreated), failures as
+/// [`BufferedViewCreateFailed`](crate::command_buffer::BufferedViewCreateFailed).
+///
+/// # Safety
+/// On success `url_raw_ptr` of `result` must point to `url_len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_on_view_created(
+    app: AppHandle,
+    request_id: u64,
+    result: CreateViewResultCpp,
+) -> AppStatus {
+    if matches!(result, CreateViewResultCpp::Success { url_raw_ptr, .. } if url_raw_ptr.is_null()) {
+        return report_status("app_on_view_created", AppStatus::InvalidArgument);
+    }
+    let result = unsafe { defold::create_view_result(result) };
+    let result = with_app(app, |app| {
+        report_buffered_view(app.world_mut(), request_id, result)
+    });
+    status_of("app_on_view_created", result)
+}
+
+#[cfg(feature = "snapshot")]
+/// Serializes the app into `out_buf`. The blob length is always written to `out_len`,
+/// when it exceeds `cap` nothing is copied and `BufferTooSmall` is returned, so the
+/// host may call with `cap == 0` first to size its buffer.
+///
+/// # Safety
+/// `out_buf` must point to `cap` writable bytes or be null when `cap` is `0`,
+/// `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_save_snapshot(
+    app: AppHandle,
+    out_buf: *mut u8,
+    cap: usize,
+    out_len: *mut usize,
+) -> AppStatus {
+    if out_len.is_null() || (out_buf.is_null() && cap != 0) {
+        return report_status("app_save_snapshot", AppStatus::InvalidArgument);
+    }
+    let result = with_app(app, |app| {
+        let blob = crate::snapshot::save_world(app.world_mut())
+            .map_err(|error| snapshot_status("app_save_snapshot", error))?;
+        unsafe { *out_len = blob.len() };
+        if blob.len() > cap {
+            return Err(AppStatus::BufferTooSmall);
+        }
+        unsafe { core::ptr::copy_nonoverlapping(blob.as_ptr(), out_buf, blob.len()) };
+        Ok(())
+    });
+    match result.and_then(|result| result) {
+        // Expected while the host sizes its buffer, not worth an error in the console.
+        Err(AppStatus::BufferTooSmall) => AppStatus::BufferTooSmall,
+        result => status_of("app_save_snapshot", result),
+    }
+}
+
+#[cfg(feature = "snapshot")]
+/// Replaces the saved entities and resources of the app with the ones in the blob
+/// written by [`app_save_snapshot`].
+///
+/// # Safety
+/// `ptr` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_load_snapshot(
+    app: AppHandle,
+    ptr: *const u8,
+    len: usize,
+) -> AppStatus {
+    if ptr.is_null() {
+        return report_status("app_load_snapshot", AppStatus::InvalidArgument);
+    }
+    let blob = unsafe { from_raw_parts(ptr, len) };
+    let result = with_app(app, |app| {
+        crate::snapshot::load_world(app.world_mut(), blob)
+            .map_err(|error| snapshot_status("app_load_snapshot", error))
+    });
+    match result {
+        Ok(result) => result.err().unwrap_or(AppStatus::Ok),
+        Err(status) => report_status("app_load_snapshot", status),
+    }
+}
+
+/// Freezes the gameplay clock and [`SimulationSet`](crate::simulation::SimulationSet),
+/// frames keep processing messages and input.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_pause(app: AppHandle) -> AppStatus {
+    status_of(
+        "app_pause",
+        with_app(app, |app| pause_simulation(app.world_mut())),
+    )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn app_resume(app: AppHandle) -> AppStatus {
+    status_of(
+        "app_resume",
+        with_app(app, |app| resume_simulation(app.world_mut())),
+    )
+}
+
+/// Runs `n_frames` frames of `dt_seconds` on a paused app right away, it stays paused.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_step(app: AppHandle, n_frames: u32, dt_seconds: f32) -> AppStatus {
+    let result = with_app(app, |app| step_simulation(app, n_frames, dt_seconds));
+    status_of("app_step", result.and_then(|result| result))
+}
+
+/// Scales how fast gameplay time passes relative to the frame time, `0` stops it.
+#[unsafe(no_mangle)]
+pub extern "C" fn app_set_time_scale(app: AppHandle, scale: f32) -> AppStatus {
+    let result = with_app(app, |app| set_time_scale(app.world_mut(), scale));
+    status_of("app_set_time_scale", result.and_then(|result| result))
+}
+
+/// Calls the Rust handler registered for `method` with JSON arguments and writes its JSON
+/// reply into `out_buf`. The reply length is always written to `out_len`, when it exceeds
+/// `out_cap` nothing is copied and `BufferTooSmall` is returned.
+///
+/// # Safety
+/// `args_json` must point to `len` readable bytes or be null when `len` is `0`, `out_buf`
+/// must point to `out_cap` writable bytes or be null when `out_cap` is `0`, `out_len` must
+/// be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn app_call(
+    app: AppHandle,
+    method: dmhash_t,
+    args_json: *const u8,
+    len: usize,
+    out_buf: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> AppStatus {
+    if out_len.is_null() || (args_json.is_null() && len != 0) || (out_buf.is_null() && out_cap != 0)
+    {
+        return report_status("app_call", AppStatus::InvalidArgument);
+    }
+    let args = if len == 0 {
+        &[]
+    } else {
+        unsafe { from_raw_parts(args_json, len) }
+    };
+
+    let result = with_app(app, |app| {
+        let reply = call_rpc(app.world_mut(), method, args).map_err(|error| match error {
+            RpcError::UnknownMethod => AppStatus::UnknownMethod,
+            RpcError::InvalidArguments(_) => AppStatus::InvalidJson,
+            RpcError::HandlerFailed | RpcError::ReplyTooLarge => AppStatus::HandlerFailed,
+            RpcError::InvalidReply => AppStatus::InvalidReply,
+        })?;
+        unsafe { *out_len = reply.len() };
+        if reply.len() > out_cap {
+            return Err(AppStatus::BufferTooSmall);
+        }
+        unsafe { core::ptr::copy_nonoverlapping(reply.as_ptr(), out_buf, reply.len()) };
+        Ok(())
+    });
+    match result.and_then(|result| result) {
+        Err(AppStatus::BufferTooSmall) => AppStatus::BufferTooSmall,
+        result => status_of("app_call", result),
+    }
 }
@@ -1,7 +1,9 @@
+use bevy_ecs::system::Resource;
 use bevy_math::{IVec2, Vec2};
 
 use crate::graph::{Graph, graph_key::EdgeKey};
 
+#[derive(Resource)]
 pub(crate) struct Board {
     root: Vec2,
     offset: Vec2,
@@ -67,6 +69,62 @@ impl Board {
     }
 }
 
+#[cfg(feature = "snapshot")]
+mod snapshot {
+    use alloc::vec::Vec;
+
+    use bevy_math::I16Vec3;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::snapshot::SnapshotResource;
+
+    #[derive(Serialize, Deserialize)]
+    pub(crate) struct BoardSnapshot {
+        root: [f32; 2],
+        offset: [f32; 2],
+        bounds_min: [i32; 2],
+        bounds_max: [i32; 2],
+        /// Blocked moves as `(from, to)` cells.
+        blocked_moves: Vec<([i16; 3], [i16; 3])>,
+    }
+
+    impl SnapshotResource for Board {
+        const KEY: &'static str = "board";
+        type Saved = BoardSnapshot;
+
+        fn save(&self) -> BoardSnapshot {
+            BoardSnapshot {
+                root: self.root.to_array(),
+                offset: self.offset.to_array(),
+                bounds_min: self.bounds_min.to_array(),
+                bounds_max: self.bounds_max.to_array(),
+                blocked_moves: self
+                    .movement_graph
+                    .one_way_edges()
+                    .into_iter()
+                    .map(|(edge, _)| (edge.from.0.to_array(), edge.to.0.to_array()))
+                    .collect(),
+            }
+        }
+
+        fn restore(saved: BoardSnapshot) -> Option<Self> {
+            let mut movement_graph = Graph::new();
+            for (from, to) in saved.blocked_moves {
+                let edge = EdgeKey::new(I16Vec3::from_array(from), I16Vec3::from_array(to))?;
+                movement_graph.insert_edge_one_way(edge, ());
+            }
+            Board::new(
+                Vec2::from_array(saved.root),
+                Vec2::from_array(saved.offset),
+                IVec2::from_array(saved.bounds_min),
+                IVec2::from_array(saved.bounds_max),
+                movement_graph,
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
@@ -420,4 +478,31 @@ mod tests {
             assert_eq!(board.world_to_grid_space(point), expected);
         }
     }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_snapshot_round_trip() {
+        let from = I16Vec3::new(0, 0, 0);
+        let to = I16Vec3::new(1, 0, 0);
+        let mut movement_graph = Graph::new();
+        movement_graph.insert_edge_one_way(EdgeKey::new(from, to).unwrap(), ());
+        let board = Board::new(
+            Vec2::new(1.0, 2.0),
+            Vec2::new(32.0, 32.0),
+            IVec2::new(0, 0),
+            IVec2::new(4, 4),
+            movement_graph,
+        )
+        .unwrap();
+
+        let restored = <Board as crate::snapshot::SnapshotResource>::restore(
+            crate::snapshot::SnapshotResource::save(&board),
+        )
+        .unwrap();
+
+        assert_eq!(restored.root, board.root);
+        assert_eq!(restored.bounds_max, board.bounds_max);
+        assert!(restored.is_movement_blocked(EdgeKey::new(from, to).unwrap()));
+        assert!(!restored.is_movement_blocked(EdgeKey::new(to, from).unwrap()));
+    }
 }
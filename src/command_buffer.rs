@@ -0,0 +1,588 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{event::Event, system::Resource, world::World};
+use bevy_transform::components::Transform;
+use serde::Serialize;
+
+use crate::{
+    defold::{
+        CreateViewError, MessageEncoding, MessageName, MessagePayload, PayloadError, URL,
+        payload_to_binary, payload_to_json,
+    },
+    defold_cpp_interface::{DefoldCommandCpp, GoTransform, HashedOrStringUrl, HashedUrl, dmhash_t},
+    messages::{DefoldMessage, message_name},
+    view::LiveViews,
+};
+
+/// Receiver of a queued command, the string is only stored when there is no hash.
+enum PendingUrl {
+    Hashed(HashedUrl),
+    String(Range<usize>),
+}
+
+enum PendingCommand {
+    SetTransform {
+        url: PendingUrl,
+        transform: GoTransform,
+    },
+    PostMessage {
+        url: PendingUrl,
+        message_id: dmhash_t,
+        message_name: Range<usize>,
+        message_data: Range<usize>,
+        encoding: MessageEncoding,
+    },
+    CreateView {
+        request_id: u64,
+        view_factory_id: dmhash_t,
+        transform: GoTransform,
+        properties_data: Range<usize>,
+    },
+    DeleteView {
+        url: PendingUrl,
+        view_url: URL,
+        recursive: bool,
+    },
+}
+
+/// Outbound commands collected during a frame and handed to C++ at once by
+/// `app_take_commands`. The direct functions in [`crate::defold`] stay available as
+/// the immediate mode.
+#[derive(Resource, Default)]
+pub struct DefoldCommandBuffer {
+    commands: Vec<PendingCommand>,
+    /// Strings and payloads of all commands, strings are stored null terminated.
+    data: Vec<u8>,
+    next_request_id: u64,
+}
+
+impl DefoldCommandBuffer {
+    fn push_bytes(&mut self, bytes: &[u8]) -> Range<usize> {
+        let start = self.data.len();
+        self.data.extend_from_slice(bytes);
+        start..self.data.len()
+    }
+
+    fn push_str(&mut self, string: &str) -> Range<usize> {
+        let range = self.push_bytes(string.as_bytes());
+        self.data.push(0);
+        range
+    }
+
+    fn push_url(&mut self, url: URL) -> PendingUrl {
+        match url.hashed() {
+            Some(hashed_url) => PendingUrl::Hashed(hashed_url),
+            None => PendingUrl::String(self.push_str(url.as_str())),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn set_go_transform(&mut self, receiver_url: URL, transform_to_set: Transform) {
+        let url = self.push_url(receiver_url);
+        self.commands.push(PendingCommand::SetTransform {
+            url,
+            transform: transform_to_set.into(),
+        });
+    }
+
+    fn push_message(
+        &mut self,
+        receiver_url: URL,
+        message_name: MessageName,
+        message_data: &[u8],
+        encoding: MessageEncoding,
+    ) {
+        let url = self.push_url(receiver_url);
+        let message_id = message_name.hash();
+        let message_name = self.push_str(message_name.as_str());
+        let message_data = self.push_bytes(message_data);
+        self.commands.push(PendingCommand::PostMessage {
+            url,
+            message_id,
+            message_name,
+            message_data,
+            encoding,
+        });
+    }
+
+    pub fn post_message<T: Serialize>(
+        &mut self,
+        receiver_url: URL,
+        message_name: MessageName,
+        message_data: T,
+    ) -> Result<(), PayloadError> {
+        let message_data_as_json = payload_to_json(&message_data)?;
+        self.push_message(
+            receiver_url,
+            message_name,
+            &message_data_as_json,
+            MessageEncoding::Json,
+        );
+        Ok(())
+    }
+
+    /// Queued counterpart of [`crate::defold::post_message_binary_to_view`].
+    pub fn post_message_binary<T: Serialize>(
+        &mut self,
+        receiver_url: URL,
+        message_name: MessageName,
+        message_data: T,
+    ) -> Result<(), PayloadError> {
+        let message_data = payload_to_binary(&message_data)?;
+        self.push_message(
+            receiver_url,
+            message_name,
+            &message_data,
+            MessageEncoding::Binary,
+        );
+        Ok(())
+    }
+
+    /// Queues `message_data` in the encoding chosen by its type.
+    pub fn post_message_payload<T: MessagePayload>(
+        &mut self,
+        receiver_url: URL,
+        message_name: MessageName,
+        message_data: T,
+    ) -> Result<(), PayloadError> {
+        match T::ENCODING {
+            MessageEncoding::Json => self.post_message(receiver_url, message_name, message_data),
+            MessageEncoding::Binary => {
+                self.post_message_binary(receiver_url, message_name, message_data)
+            }
+        }
+    }
+
+    /// Queued counterpart of [`crate::messages::send`].
+    pub fn send<M: DefoldMessage>(
+        &mut self,
+        receiver_url: URL,
+        message: M,
+    ) -> Result<(), PayloadError> {
+        self.post_message_payload(receiver_url, message_name::<M>(), message)
+    }
+
+    /// Returns the request id C++ echoes back through `app_on_view_created`, the
+    /// result arrives as [`BufferedViewCreated`] or [`BufferedViewCreateFailed`].
+    pub fn create_view<T: Serialize>(
+        &mut self,
+        view_factory_id: dmhash_t,
+        transform_to_set: Transform,
+        create_view_data: T,
+    ) -> Result<u64, PayloadError> {
+        let create_view_data_as_json = payload_to_json(&create_view_data)?;
+
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let properties_data = self.push_bytes(&create_view_data_as_json);
+        self.commands.push(PendingCommand::CreateView {
+            request_id,
+            view_factory_id,
+            transform: transform_to_set.into(),
+            properties_data,
+        });
+        Ok(request_id)
+    }
+
+    /// The view stops being tracked by [`LiveViews`] once the command is taken.
+    pub fn delete_view(&mut self, view_url: URL, recursive: bool) {
+        let url = self.push_url(view_url);
+        self.commands.push(PendingCommand::DeleteView {
+            url,
+            view_url,
+            recursive,
+        });
+    }
+}
+
+/// View queued with [`DefoldCommandBuffer::create_view`] was created, it is already
+/// registered in [`LiveViews`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct BufferedViewCreated {
+    pub request_id: u64,
+    pub url: URL,
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct BufferedViewCreateFailed {
+    pub request_id: u64,
+    pub error: CreateViewError,
+}
+
+/// Result of a queued view creation reported by C++.
+pub(crate) fn report_buffered_view(
+    world: &mut World,
+    request_id: u64,
+    result: Result<URL, CreateViewError>,
+) {
+    match result {
+        Ok(url) => {
+            if let Some(mut live_views) = world.get_resource_mut::<LiveViews>() {
+                live_views.track(url);
+            }
+            world.send_event(BufferedViewCreated { request_id, url });
+        }
+        Err(error) => {
+            world.send_event(BufferedViewCreateFailed { request_id, error });
+        }
+    }
+}
+
+/// Commands handed to C++ by the last `app_take_commands`, kept alive until the next call.
+#[derive(Default)]
+pub(crate) struct TakenDefoldCommands {
+    data: Vec<u8>,
+    records: Vec<DefoldCommandCpp>,
+}
+
+/// Moves the pending commands out of the buffer and returns them as C records.
+/// The returned slice stays valid until the next call for the same world.
+pub(crate) fn take_defold_commands(world: &mut World) -> &[DefoldCommandCpp] {
+    let mut taken = world
+        .remove_non_send_resource::<TakenDefoldCommands>()
+        .unwrap_or_default();
+    taken.records.clear();
+    taken.data.clear();
+
+    let mut buffer = world.get_resource_or_insert_with(DefoldCommandBuffer::default);
+    // Swap the storages so both vectors keep their capacity between frames.
+    core::mem::swap(&mut taken.data, &mut buffer.data);
+    let commands = core::mem::take(&mut buffer.commands);
+    if let Some(mut live_views) = world.get_resource_mut::<LiveViews>() {
+        for command in &commands {
+            if let PendingCommand::DeleteView { view_url, .. } = command {
+                live_views.untrack(*view_url);
+            }
+        }
+    }
+
+    let data = taken.data.as_ptr();
+    let ptr = |range: &Range<usize>| data.wrapping_add(range.start);
+    let url = |url: &PendingUrl| match url {
+        PendingUrl::Hashed(hashed_url) => HashedOrStringUrl::Hashed(*hashed_url),
+        PendingUrl::String(range) => HashedOrStringUrl::String(ptr(range)),
+    };
+    taken
+        .records
+        .extend(commands.iter().map(|command| match command {
+            PendingCommand::SetTransform {
+                url: receiver_url,
+                transform,
+            } => DefoldCommandCpp::SetTransform {
+                url: url(receiver_url),
+                transform: *transform,
+            },
+            PendingCommand::PostMessage {
+                url: receiver_url,
+                message_id,
+                message_name,
+                message_data,
+                encoding: MessageEncoding::Json,
+            } => DefoldCommandCpp::PostMessage {
+                url: url(receiver_url),
+                message_id: *message_id,
+                message_name: ptr(message_name),
+                message_data: ptr(message_data),
+                message_data_len: message_data.len(),
+            },
+            PendingCommand::PostMessage {
+                url: receiver_url,
+                message_id,
+                message_name,
+                message_data,
+                encoding: MessageEncoding::Binary,
+            } => DefoldCommandCpp::PostBinaryMessage {
+                url: url(receiver_url),
+                message_id: *message_id,
+                message_name: ptr(message_name),
+                message_data: ptr(message_data),
+                message_data_len: message_data.len(),
+            },
+            PendingCommand::CreateView {
+                request_id,
+                view_factory_id,
+                transform,
+                properties_data,
+            } => DefoldCommandCpp::CreateView {
+                request_id: *request_id,
+                view_factory_id: *view_factory_id,
+                transform: *transform,
+                properties_data: ptr(properties_data),
+                properties_data_len: properties_data.len(),
+            },
+            PendingCommand::DeleteView {
+                url: view_url,
+                recursive,
+                ..
+            } => DefoldCommandCpp::DeleteView {
+                url: url(view_url),
+                recursive: *recursive,
+            },
+        }));
+
+    world.insert_non_send_resource(taken);
+    &world.non_send_resource::<TakenDefoldCommands>().records
+}
+
+pub struct DefoldCommandBufferPlugin;
+impl Plugin for DefoldCommandBufferPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DefoldCommandBuffer>()
+            .add_event::<BufferedViewCreated>()
+            .add_event::<BufferedViewCreateFailed>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{ffi::CStr, slice::from_raw_parts};
+
+    use bevy_app::Update;
+    use bevy_ecs::system::ResMut;
+    use bevy_math::Vec3;
+    use no_std_strings::{ztr32, ztr64};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::dmhash;
+
+    #[derive(Serialize)]
+    struct Hit {
+        damage: u32,
+    }
+
+    fn url() -> URL {
+        URL::new(ztr64::create("main:/unit#script"))
+    }
+
+    const HASHED_URL: HashedUrl = HashedUrl {
+        socket: dmhash!("main"),
+        path: dmhash!("/view"),
+        fragment: 0,
+    };
+
+    fn hashed_url() -> URL {
+        URL::with_hashed(ztr64::create("main:/view"), HASHED_URL)
+    }
+
+    fn string_url(url: &HashedOrStringUrl) -> &'static str {
+        match url {
+            HashedOrStringUrl::String(ptr) => c_str(*ptr),
+            HashedOrStringUrl::Hashed(hashed_url) => {
+                panic!("unexpected hashed url {:?}", hashed_url)
+            }
+        }
+    }
+
+    fn c_str(ptr: *const u8) -> &'static str {
+        unsafe { CStr::from_ptr(ptr as *const cty::c_char) }
+            .to_str()
+            .unwrap()
+    }
+
+    fn bytes(ptr: *const u8, len: usize) -> &'static [u8] {
+        unsafe { from_raw_parts(ptr, len) }
+    }
+
+    fn queue_commands(mut buffer: ResMut<DefoldCommandBuffer>) {
+        buffer.set_go_transform(url(), Transform::from_translation(Vec3::new(1.0, 2.0, 3.0)));
+        buffer
+            .post_message(
+                url(),
+                MessageName::new(ztr32::create("hit")),
+                Hit { damage: 3 },
+            )
+            .unwrap();
+        assert_eq!(buffer.create_view(42, Transform::IDENTITY, ()).unwrap(), 0);
+        buffer.delete_view(hashed_url(), true);
+    }
+
+    #[test]
+    fn test_take_commands_returns_records_in_order() {
+        let mut app = App::new();
+        app.add_plugins(DefoldCommandBufferPlugin)
+            .add_systems(Update, queue_commands);
+        app.update();
+
+        let records = take_defold_commands(app.world_mut());
+        assert_eq!(records.len(), 4);
+
+        match &records[0] {
+            DefoldCommandCpp::SetTransform { url, transform } => {
+                assert_eq!(string_url(url), "main:/unit#script");
+                assert_eq!(transform.translation, [1.0, 2.0, 3.0]);
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+        match &records[1] {
+            DefoldCommandCpp::PostMessage {
+                url,
+                message_id,
+                message_name,
+                message_data,
+                message_data_len,
+            } => {
+                assert_eq!(string_url(url), "main:/unit#script");
+                assert_eq!(*message_id, dmhash!("hit"));
+                assert_eq!(c_str(*message_name), "hit");
+                assert_eq!(bytes(*message_data, *message_data_len), br#"{"damage":3}"#);
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+        match &records[2] {
+            DefoldCommandCpp::CreateView {
+                request_id,
+                view_factory_id,
+                properties_data,
+                properties_data_len,
+                ..
+            } => {
+                assert_eq!(*request_id, 0);
+                assert_eq!(*view_factory_id, 42);
+                assert_eq!(bytes(*properties_data, *properties_data_len), b"null");
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+        match &records[3] {
+            DefoldCommandCpp::DeleteView { url, recursive } => {
+                assert_eq!(*url, HashedOrStringUrl::Hashed(HASHED_URL));
+                assert!(*recursive);
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_take_commands_drains_buffer() {
+        let mut app = App::new();
+        app.add_plugins(DefoldCommandBufferPlugin)
+            .add_systems(Update, queue_commands);
+        app.update();
+
+        assert_eq!(take_defold_commands(app.world_mut()).len(), 4);
+        assert!(app.world().resource::<DefoldCommandBuffer>().is_empty());
+        assert!(take_defold_commands(app.world_mut()).is_empty());
+    }
+
+    #[test]
+    fn test_post_message_accepts_payload_over_512_bytes() {
+        let mut buffer = DefoldCommandBuffer::default();
+        let path = [7u32; 400];
+
+        buffer
+            .post_message(url(), MessageName::new(ztr32::create("path")), &path[..])
+            .unwrap();
+
+        match &buffer.commands[0] {
+            PendingCommand::PostMessage { message_data, .. } => {
+                assert_eq!(message_data.len(), 2 + 400 * 2 - 1);
+            }
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct UnitMoved {
+        id: u32,
+        cell: [i16; 2],
+    }
+
+    impl MessagePayload for UnitMoved {
+        const ENCODING: MessageEncoding = MessageEncoding::Binary;
+    }
+
+    impl DefoldMessage for UnitMoved {
+        const NAME: &'static str = "unit_moved";
+    }
+
+    #[test]
+    fn test_binary_payload_becomes_binary_record() {
+        let mut buffer = DefoldCommandBuffer::default();
+        buffer
+            .post_message_payload(
+                url(),
+                MessageName::new(ztr32::create("unit_moved")),
+                UnitMoved {
+                    id: 5,
+                    cell: [1, -1],
+                },
+            )
+            .unwrap();
+        let mut app = App::new();
+        app.insert_resource(buffer);
+
+        let records = take_defold_commands(app.world_mut());
+        match &records[0] {
+            DefoldCommandCpp::PostBinaryMessage {
+                message_name,
+                message_data,
+                message_data_len,
+                ..
+            } => {
+                assert_eq!(c_str(*message_name), "unit_moved");
+                assert_eq!(bytes(*message_data, *message_data_len), [5, 2, 1]);
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_taken_delete_untracks_view() {
+        let mut app = App::new();
+        app.add_plugins((DefoldCommandBufferPlugin, crate::view::DefoldViewPlugin));
+        let world = app.world_mut();
+        report_buffered_view(world, 0, Ok(hashed_url()));
+        report_buffered_view(world, 1, Ok(url()));
+
+        world
+            .resource_mut::<DefoldCommandBuffer>()
+            .delete_view(URL::parse("main:/view").unwrap(), false);
+        assert_eq!(world.resource::<LiveViews>().len(), 2);
+        take_defold_commands(world);
+
+        assert_eq!(world.resource::<LiveViews>().len(), 1);
+    }
+
+    #[test]
+    fn test_reported_view_is_tracked_and_announced() {
+        let mut app = App::new();
+        app.add_plugins((DefoldCommandBufferPlugin, crate::view::DefoldViewPlugin));
+        let world = app.world_mut();
+
+        report_buffered_view(world, 0, Ok(url()));
+        report_buffered_view(world, 1, Err(CreateViewError::NoViewFactory));
+
+        assert_eq!(world.resource::<LiveViews>().len(), 1);
+        let created: Vec<_> = world
+            .resource_mut::<bevy_ecs::event::Events<BufferedViewCreated>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            created,
+            [BufferedViewCreated {
+                request_id: 0,
+                url: url(),
+            }]
+        );
+        let failed: Vec<_> = world
+            .resource_mut::<bevy_ecs::event::Events<BufferedViewCreateFailed>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            failed,
+            [BufferedViewCreateFailed {
+                request_id: 1,
+                error: CreateViewError::NoViewFactory,
+            }]
+        );
+    }
+}
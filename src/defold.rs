@@ -1,24 +1,29 @@
-use core::{slice::from_raw_parts, str::FromStr};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::{
+    fmt::{self, Write},
+    slice::from_raw_parts,
+    sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering},
+};
 
+use bevy_math::{Quat, Vec3};
 use bevy_transform::components::Transform;
 use no_std_strings::{zstr, ztr32, ztr64};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::hash;
+pub use crate::url::{URL, UrlError};
 
 use crate::defold_cpp_interface::{
-    create_view_cpp, dmHashReverseSafe64, dmHashString64, dmhash_t, log_error_cpp, log_info_cpp,
-    post_message_cpp, set_go_transform_cpp,
+    AnimValue, AnimateResultCpp, CreateViewResultCpp, DeleteViewResultCpp, Easing,
+    GetGoTransformResultCpp, GoTransformBatchEntry, HashedUrl, Playback, animate_property_cpp,
+    create_view_cpp, delete_view_cpp, delete_view_hashed_cpp, dmHashReverseSafe64, dmhash_t,
+    get_go_transform_cpp, get_go_transform_hashed_cpp, log_debug_cpp, log_error_cpp, log_info_cpp,
+    log_warn_cpp, post_message_binary_cpp, post_message_binary_hashed_cpp, post_message_cpp,
+    post_message_hashed_cpp, set_go_position_cpp, set_go_rotation_cpp, set_go_transform_cpp,
+    set_go_transform_hashed_cpp, set_go_transforms_batch_cpp,
 };
 
-#[derive(Clone, Copy)]
-pub struct URL(ztr64);
-
-impl URL {
-    pub fn new(url: ztr64) -> Self {
-        Self(url)
-    }
-}
-
 #[derive(Clone, Copy)]
 pub struct MessageName(ztr32);
 
@@ -26,32 +31,312 @@ impl MessageName {
     pub fn new(name: ztr32) -> Self {
         Self(name)
     }
+
+    pub fn as_str(&self) -> &str {
+        self.0.to_str()
+    }
+
+    /// Message id the engine matches on, `hash(name)`.
+    pub fn hash(&self) -> dmhash_t {
+        hash::hash_string(self.as_str())
+    }
+}
+
+/// Default for [`set_max_payload_len`].
+pub const DEFAULT_MAX_PAYLOAD_LEN: usize = 64 * 1024;
+
+static MAX_PAYLOAD_LEN: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PAYLOAD_LEN);
+
+/// Sets the largest JSON payload, in bytes, sent with a message or as view properties.
+/// The cap is shared by every world of the process.
+pub fn set_max_payload_len(max_len: usize) {
+    MAX_PAYLOAD_LEN.store(max_len, Ordering::Relaxed);
+}
+
+pub fn max_payload_len() -> usize {
+    MAX_PAYLOAD_LEN.load(Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadError {
+    /// The value can't be written as JSON.
+    Serialize,
+    /// The JSON is longer than the allowed maximum.
+    TooLarge,
+}
+
+/// Serializes `value` to JSON in a buffer grown as needed, up to `max_len` bytes.
+pub(crate) fn to_json_vec<T: Serialize + ?Sized>(
+    value: &T,
+    max_len: usize,
+) -> Result<Vec<u8>, PayloadError> {
+    let mut buffer = vec![0; max_len.min(256)];
+    loop {
+        match serde_json_core::to_slice(value, &mut buffer) {
+            Ok(len) => {
+                buffer.truncate(len);
+                return Ok(buffer);
+            }
+            Err(serde_json_core::ser::Error::BufferFull) if buffer.len() < max_len => {
+                let grown_len = buffer.len().saturating_mul(2).clamp(1, max_len);
+                buffer.resize(grown_len, 0);
+            }
+            Err(serde_json_core::ser::Error::BufferFull) => return Err(PayloadError::TooLarge),
+            Err(_) => return Err(PayloadError::Serialize),
+        }
+    }
+}
+
+/// Serializes a message or view payload, up to [`max_payload_len`] bytes.
+pub(crate) fn payload_to_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, PayloadError> {
+    to_json_vec(value, max_payload_len())
 }
 
 pub fn post_message_to_view<T: Serialize>(
     receiver_url: URL,
     message_name: MessageName,
     message_data: T,
-) {
-    let message_data_as_json: serde_json_core::heapless::String<512> =
-        serde_json_core::to_string(&message_data).unwrap_or_else(|_| {
-            log_info(ztr64::create("cant serialize message to json"));
-            serde_json_core::heapless::String::from_str("{}").unwrap()
-        });
+) -> Result<(), PayloadError> {
+    if let Some(hashed_url) = receiver_url.hashed() {
+        return post_message_hashed(hashed_url, message_name.hash(), message_data);
+    }
+    let message_data_as_json = payload_to_json(&message_data)?;
 
     unsafe {
         post_message_cpp(
-            receiver_url.0.as_ptr(),
+            receiver_url.as_ptr(),
+            message_name.0.as_ptr(),
+            message_data_as_json.as_ptr(),
+            message_data_as_json.len(),
+        )
+    };
+    Ok(())
+}
+
+/// Encodes `value` in the binary layout described on [`post_message_binary_to_view`],
+/// up to [`max_payload_len`] bytes.
+pub(crate) fn payload_to_binary<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, PayloadError> {
+    let bytes = postcard::to_allocvec(value).map_err(|_| PayloadError::Serialize)?;
+    if bytes.len() > max_payload_len() {
+        return Err(PayloadError::TooLarge);
+    }
+    Ok(bytes)
+}
+
+/// Posts `message_data` in postcard's compact layout instead of JSON, for messages
+/// sent every frame. The layout carries no field names, so the Lua reader follows
+/// the Rust type:
+/// - struct and tuple fields in declaration order,
+/// - `u16`..`u128` as LEB128 varints, signed integers zigzag encoded first,
+/// - `u8`, `i8` and `bool` as one byte, `f32` and `f64` little endian,
+/// - strings, byte slices, sequences and maps prefixed with their varint length,
+/// - `Option` as a `0`/`1` byte followed by the value,
+/// - enums as the varint index of the variant followed by its fields.
+pub fn post_message_binary_to_view<T: Serialize>(
+    receiver_url: URL,
+    message_name: MessageName,
+    message_data: T,
+) -> Result<(), PayloadError> {
+    if let Some(hashed_url) = receiver_url.hashed() {
+        return post_message_binary_hashed(hashed_url, message_name.hash(), message_data);
+    }
+    let message_data = payload_to_binary(&message_data)?;
+
+    unsafe {
+        post_message_binary_cpp(
+            receiver_url.as_ptr(),
             message_name.0.as_ptr(),
+            message_data.as_ptr(),
+            message_data.len(),
+        )
+    };
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageEncoding {
+    #[default]
+    Json,
+    /// See [`post_message_binary_to_view`].
+    Binary,
+}
+
+/// Message payload that picks its own encoding, JSON unless it opts into binary.
+pub trait MessagePayload: Serialize {
+    const ENCODING: MessageEncoding = MessageEncoding::Json;
+}
+
+/// Posts `message_data` in the encoding chosen by its type.
+pub fn post_message_payload<T: MessagePayload>(
+    receiver_url: URL,
+    message_name: MessageName,
+    message_data: T,
+) -> Result<(), PayloadError> {
+    match T::ENCODING {
+        MessageEncoding::Json => post_message_to_view(receiver_url, message_name, message_data),
+        MessageEncoding::Binary => {
+            post_message_binary_to_view(receiver_url, message_name, message_data)
+        }
+    }
+}
+
+/// Hashed counterpart of [`post_message_to_view`], the message is named by its id.
+pub fn post_message_hashed<T: Serialize>(
+    receiver_url: HashedUrl,
+    message_id: dmhash_t,
+    message_data: T,
+) -> Result<(), PayloadError> {
+    let message_data_as_json = payload_to_json(&message_data)?;
+
+    unsafe {
+        post_message_hashed_cpp(
+            receiver_url,
+            message_id,
             message_data_as_json.as_ptr(),
             message_data_as_json.len(),
         )
     };
+    Ok(())
 }
 
+/// Hashed counterpart of [`post_message_binary_to_view`].
+pub fn post_message_binary_hashed<T: Serialize>(
+    receiver_url: HashedUrl,
+    message_id: dmhash_t,
+    message_data: T,
+) -> Result<(), PayloadError> {
+    let message_data = payload_to_binary(&message_data)?;
+
+    unsafe {
+        post_message_binary_hashed_cpp(
+            receiver_url,
+            message_id,
+            message_data.as_ptr(),
+            message_data.len(),
+        )
+    };
+    Ok(())
+}
+
+/// Hashed counterpart of [`post_message_payload`].
+pub fn post_message_payload_hashed<T: MessagePayload>(
+    receiver_url: HashedUrl,
+    message_id: dmhash_t,
+    message_data: T,
+) -> Result<(), PayloadError> {
+    match T::ENCODING {
+        MessageEncoding::Json => post_message_hashed(receiver_url, message_id, message_data),
+        MessageEncoding::Binary => {
+            post_message_binary_hashed(receiver_url, message_id, message_data)
+        }
+    }
+}
+
+/// Goes through [`set_go_transform_hashed`] when the URL carries its hashed form.
 pub fn set_go_transform(receiver_url: URL, transform_to_set: Transform) {
+    if let Some(hashed_url) = receiver_url.hashed() {
+        return set_go_transform_hashed(hashed_url, transform_to_set);
+    }
+    unsafe {
+        set_go_transform_cpp(receiver_url.as_ptr(), transform_to_set.into());
+    }
+}
+
+pub fn set_go_transform_hashed(receiver_url: HashedUrl, transform_to_set: Transform) {
+    unsafe {
+        set_go_transform_hashed_cpp(receiver_url, transform_to_set.into());
+    }
+}
+
+/// Writes the transforms of many game objects in one call.
+pub fn set_go_transforms_batch(entries: &[GoTransformBatchEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+    unsafe {
+        set_go_transforms_batch_cpp(entries.as_ptr(), entries.len());
+    }
+}
+
+/// Id of an animation started with [`animate_property`], reported back in
+/// [`AnimationFinished`](crate::animation::AnimationFinished).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AnimationId(pub u64);
+
+static NEXT_ANIMATION_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimateError {
+    NotFound,
+    InvalidProperty,
+}
+
+/// Animates a game object property with `go.animate`, e.g. `hash("position.y")`
+/// towards `200.0` or `hash("scale")` towards a `Vec3`.
+pub fn animate_property(
+    url: URL,
+    property: dmhash_t,
+    to: impl Into<AnimValue>,
+    easing: Easing,
+    duration: f32,
+    delay: f32,
+    playback: Playback,
+) -> Result<AnimationId, AnimateError> {
+    let animation_id = NEXT_ANIMATION_ID.fetch_add(1, Ordering::Relaxed);
+    let animate_result_cpp = unsafe {
+        animate_property_cpp(
+            url.as_cpp(),
+            property,
+            to.into(),
+            easing,
+            duration,
+            delay,
+            playback,
+            animation_id,
+        )
+    };
+    match animate_result_cpp {
+        AnimateResultCpp::Success => Ok(AnimationId(animation_id)),
+        AnimateResultCpp::NotFound => Err(AnimateError::NotFound),
+        AnimateResultCpp::InvalidProperty => Err(AnimateError::InvalidProperty),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GetGoTransformError {
+    NotFound,
+}
+
+/// Reads the local transform of a game object, as `go.get_position`,
+/// `go.get_rotation` and `go.get_scale` would.
+pub fn get_go_transform(url: URL) -> Result<Transform, GetGoTransformError> {
+    let get_result_cpp = unsafe {
+        match url.hashed() {
+            Some(hashed_url) => get_go_transform_hashed_cpp(hashed_url),
+            None => get_go_transform_cpp(url.as_ptr()),
+        }
+    };
+    match get_result_cpp {
+        GetGoTransformResultCpp::Success { transform } => Ok(transform.into()),
+        GetGoTransformResultCpp::NotFound => Err(GetGoTransformError::NotFound),
+    }
+}
+
+pub fn set_go_position(receiver_url: URL, position: Vec3) {
     unsafe {
-        set_go_transform_cpp(receiver_url.0.as_ptr(), transform_to_set.into());
+        set_go_position_cpp(receiver_url.as_ptr(), position.x, position.y, position.z);
+    }
+}
+
+pub fn set_go_rotation(receiver_url: URL, rotation: Quat) {
+    unsafe {
+        set_go_rotation_cpp(
+            receiver_url.as_ptr(),
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            rotation.w,
+        );
     }
 }
 
@@ -60,7 +345,12 @@ pub enum HashToStringError {
     Unknown,
 }
 
+/// Looks the name up in the Rust reverse table first, then asks the engine.
 pub fn hash_to_string(hash: dmhash_t) -> Result<ztr64, HashToStringError> {
+    if let Some(name) = hash::with_reverse_hash(hash, ztr64::create) {
+        return Ok(name);
+    }
+
     let c_string_ptr = unsafe { dmHashReverseSafe64(hash) };
     if c_string_ptr.is_null() {
         return Err(HashToStringError::GetNull);
@@ -76,9 +366,10 @@ pub fn hash_to_string(hash: dmhash_t) -> Result<ztr64, HashToStringError> {
 }
 
 pub fn string_to_hash(string_to_convert: &ztr64) -> dmhash_t {
-    unsafe { dmHashString64(string_to_convert.as_ptr() as *const cty::c_char) }
+    hash::hash_string(string_to_convert.to_str())
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CreateViewError {
     NoViewFactory,
     CallbackCallError,
@@ -87,67 +378,453 @@ pub enum CreateViewError {
     GetNullAfterCreate,
     CallbackSetupError,
     InvalidLuaContext,
+    InvalidProperties(PayloadError),
+    /// The engine reported a URL that does not fit or is not UTF-8.
+    InvalidUrl(UrlError),
 }
 
-pub fn create_view<T: Serialize>(
+/// Untracked, apps create views through [`crate::view::LiveViews::create_view`] so
+/// they are deleted with the app.
+pub(crate) fn create_view<T: Serialize>(
     view_factory_id: dmhash_t,
     transform_to_set: Transform,
     create_view_data: T,
 ) -> Result<URL, CreateViewError> {
-    let create_view_data_as_json: serde_json_core::heapless::String<512> =
-        serde_json_core::to_string(&create_view_data).unwrap_or_else(|_| {
-            log_info(ztr64::create("cant serialize create_view_data to json"));
-            serde_json_core::heapless::String::from_str("{}").unwrap()
-        });
+    let create_view_data_as_json =
+        payload_to_json(&create_view_data).map_err(CreateViewError::InvalidProperties)?;
+
+    create_view_from_json(view_factory_id, transform_to_set, &create_view_data_as_json)
+}
 
+/// Same as [`create_view`] for properties that are already serialized to JSON,
+/// they are held to the same [`max_payload_len`].
+pub(crate) fn create_view_from_json(
+    view_factory_id: dmhash_t,
+    transform_to_set: Transform,
+    properties_json: &[u8],
+) -> Result<URL, CreateViewError> {
+    if properties_json.len() > max_payload_len() {
+        return Err(CreateViewError::InvalidProperties(PayloadError::TooLarge));
+    }
     let create_result_cpp = unsafe {
         create_view_cpp(
             view_factory_id,
             transform_to_set.into(),
-            create_view_data_as_json.as_ptr(),
-            create_view_data_as_json.len(),
+            properties_json.as_ptr(),
+            properties_json.len(),
         )
     };
+    unsafe { create_view_result(create_result_cpp) }
+}
+
+/// Reads the result of a view creation reported by C++.
+///
+/// # Safety
+/// On success `url_raw_ptr` must point to `url_len` readable bytes.
+pub(crate) unsafe fn create_view_result(
+    create_result_cpp: CreateViewResultCpp,
+) -> Result<URL, CreateViewError> {
     match create_result_cpp {
-        crate::defold_cpp_interface::CreateViewResultCpp::Success {
+        CreateViewResultCpp::Success {
+            url,
             url_raw_ptr,
             url_len,
         } => {
             let url_as_slice = unsafe { from_raw_parts(url_raw_ptr, url_len) };
-            Ok(URL::new(ztr64::from_raw(url_as_slice)))
-        }
-        crate::defold_cpp_interface::CreateViewResultCpp::NoViewFactory => {
-            Err(CreateViewError::NoViewFactory)
+            URL::from_engine(url_as_slice, url).map_err(CreateViewError::InvalidUrl)
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::GetNullAfterCreate => {
-            Err(CreateViewError::GetNullAfterCreate)
+        CreateViewResultCpp::NoViewFactory => Err(CreateViewError::NoViewFactory),
+        CreateViewResultCpp::GetNullAfterCreate => Err(CreateViewError::GetNullAfterCreate),
+        CreateViewResultCpp::CallbackCallError => Err(CreateViewError::CallbackCallError),
+        CreateViewResultCpp::CreateViewCallbackInvalid => {
+            Err(CreateViewError::CreateViewCallbackInvalid)
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::CallbackCallError => {
-            Err(CreateViewError::CallbackCallError)
+        CreateViewResultCpp::CantParseDataToLua => Err(CreateViewError::CantParseDataToLua),
+        CreateViewResultCpp::CallbackSetupError => Err(CreateViewError::CallbackSetupError),
+        CreateViewResultCpp::InvalidLuaContext => Err(CreateViewError::InvalidLuaContext),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteViewError {
+    NotFound,
+    CallbackCallError,
+    InvalidLuaContext,
+}
+
+/// Deletes a game object created with [`create_view`], with its children when
+/// `recursive` is set. Untracked like [`create_view`], apps go through
+/// [`crate::view::LiveViews::delete`].
+pub(crate) fn delete_view(view_url: URL, recursive: bool) -> Result<(), DeleteViewError> {
+    let delete_result_cpp = unsafe {
+        match view_url.hashed() {
+            Some(hashed_url) => delete_view_hashed_cpp(hashed_url, recursive),
+            None => delete_view_cpp(view_url.as_ptr(), recursive),
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::CreateViewCallbackInvalid => {
-            Err(CreateViewError::CreateViewCallbackInvalid)
+    };
+    match delete_result_cpp {
+        DeleteViewResultCpp::Success => Ok(()),
+        DeleteViewResultCpp::NotFound => Err(DeleteViewError::NotFound),
+        DeleteViewResultCpp::CallbackCallError => Err(DeleteViewError::CallbackCallError),
+        DeleteViewResultCpp::InvalidLuaContext => Err(DeleteViewError::InvalidLuaContext),
+    }
+}
+
+/// Minimum level a message needs to reach the Defold console.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+
+static MIN_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Applies to every world of the process.
+pub fn set_min_log_level(level: LogLevel) {
+    MIN_LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+    update_log_max_level();
+}
+
+fn is_log_level_enabled(level: LogLevel) -> bool {
+    level as u8 >= MIN_LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+fn log_line(level: LogLevel, message: *const u8) {
+    if !is_log_level_enabled(level) {
+        return;
+    }
+    unsafe {
+        match level {
+            LogLevel::Debug => log_debug_cpp(message),
+            LogLevel::Info => log_info_cpp(message),
+            LogLevel::Warn => log_warn_cpp(message),
+            LogLevel::Error => log_error_cpp(message),
+            LogLevel::Off => {}
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::CantParseDataToLua => {
-            Err(CreateViewError::CantParseDataToLua)
+    }
+}
+
+pub fn log_debug<const N: usize>(message: zstr<N>) {
+    log_line(LogLevel::Debug, message.as_ptr());
+}
+
+pub fn log_info<const N: usize>(message: zstr<N>) {
+    log_line(LogLevel::Info, message.as_ptr());
+}
+
+pub fn log_warn<const N: usize>(message: zstr<N>) {
+    log_line(LogLevel::Warn, message.as_ptr());
+}
+
+pub fn log_error<const N: usize>(message: zstr<N>) {
+    log_line(LogLevel::Error, message.as_ptr());
+}
+
+/// Longest line the logging macros send, including the module tag. Longer
+/// messages are cut.
+pub const LOG_LINE_CAPACITY: usize = 256;
+
+/// Stack buffer the logging macros format into, it drops whatever does not fit.
+struct LogLineBuffer(zstr<LOG_LINE_CAPACITY>);
+
+impl Write for LogLineBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.push(s);
+        Ok(())
+    }
+}
+
+fn format_log_line(module_path: &str, args: fmt::Arguments) -> zstr<LOG_LINE_CAPACITY> {
+    let mut line = LogLineBuffer(zstr::new());
+    let _ = write!(line, "[{}] {}", module_path, args);
+    line.0
+}
+
+/// Backend of the `defold_*!` logging macros.
+#[doc(hidden)]
+pub fn log_fmt(level: LogLevel, module_path: &str, args: fmt::Arguments) {
+    if !is_log_level_enabled(level) {
+        return;
+    }
+    log_line(level, format_log_line(module_path, args).as_ptr());
+}
+
+/// Logs a `format!` style message at debug level, tagged with the calling module.
+#[macro_export]
+macro_rules! defold_debug {
+    ($($arg:tt)+) => {
+        $crate::defold::log_fmt(
+            $crate::defold::LogLevel::Debug,
+            core::module_path!(),
+            core::format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a `format!` style message at info level, tagged with the calling module.
+#[macro_export]
+macro_rules! defold_info {
+    ($($arg:tt)+) => {
+        $crate::defold::log_fmt(
+            $crate::defold::LogLevel::Info,
+            core::module_path!(),
+            core::format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a `format!` style message at warn level, tagged with the calling module.
+#[macro_export]
+macro_rules! defold_warn {
+    ($($arg:tt)+) => {
+        $crate::defold::log_fmt(
+            $crate::defold::LogLevel::Warn,
+            core::module_path!(),
+            core::format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a `format!` style message at error level, tagged with the calling module.
+#[macro_export]
+macro_rules! defold_error {
+    ($($arg:tt)+) => {
+        $crate::defold::log_fmt(
+            $crate::defold::LogLevel::Error,
+            core::module_path!(),
+            core::format_args!($($arg)+),
+        )
+    };
+}
+
+/// Minimum levels for `log` records by target, overriding [`set_min_log_level`] for
+/// every world of the process. A filter for `bevy_ecs` also covers `bevy_ecs::world`.
+static LOG_TARGET_FILTERS: spin::Mutex<BTreeMap<String, LogLevel>> =
+    spin::Mutex::new(BTreeMap::new());
+
+pub fn set_log_target_filters(filters: BTreeMap<String, LogLevel>) {
+    *LOG_TARGET_FILTERS.lock() = filters;
+    update_log_max_level();
+}
+
+fn min_level_for_target(filters: &BTreeMap<String, LogLevel>, target: &str) -> LogLevel {
+    filters
+        .iter()
+        .filter(|(prefix, _)| {
+            target
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or_else(
+            || LogLevel::from_u8(MIN_LOG_LEVEL.load(Ordering::Relaxed)),
+            |(_, level)| *level,
+        )
+}
+
+impl LogLevel {
+    fn from_u8(level: u8) -> Self {
+        match level {
+            0 => LogLevel::Debug,
+            1 => LogLevel::Info,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Error,
+            _ => LogLevel::Off,
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::CallbackSetupError => {
-            Err(CreateViewError::CallbackSetupError)
+    }
+
+    fn from_log(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug | log::Level::Trace => LogLevel::Debug,
         }
-        crate::defold_cpp_interface::CreateViewResultCpp::InvalidLuaContext => {
-            Err(CreateViewError::InvalidLuaContext)
+    }
+
+    fn to_log_filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Debug => log::LevelFilter::Trace,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Off => log::LevelFilter::Off,
         }
     }
 }
 
-pub fn log_info<const N: usize>(message: zstr<N>) {
-    unsafe {
-        log_info_cpp(message.as_ptr());
+/// Lets the `log` macros skip records no filter would pass before formatting them.
+fn update_log_max_level() {
+    let most_verbose = LOG_TARGET_FILTERS.lock().values().copied().fold(
+        LogLevel::from_u8(MIN_LOG_LEVEL.load(Ordering::Relaxed)),
+        LogLevel::min,
+    );
+    log::set_max_level(most_verbose.to_log_filter());
+}
+
+fn format_log_record(record: &log::Record) -> zstr<LOG_LINE_CAPACITY> {
+    match record.module_path() {
+        Some(module_path) if module_path != record.target() => format_log_line(
+            record.target(),
+            format_args!("({}) {}", module_path, record.args()),
+        ),
+        _ => format_log_line(record.target(), *record.args()),
     }
 }
 
-pub fn log_error<const N: usize>(message: zstr<N>) {
-    unsafe {
-        log_error_cpp(message.as_ptr());
+/// Backend of the `log` facade, and through it of `tracing`, that writes to the
+/// Defold console.
+struct DefoldLogger;
+
+impl log::Log for DefoldLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        let min_level = min_level_for_target(&LOG_TARGET_FILTERS.lock(), metadata.target());
+        min_level != LogLevel::Off && LogLevel::from_log(metadata.level()) >= min_level
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let level = LogLevel::from_log(record.level());
+        let line = format_log_record(record);
+        unsafe {
+            match level {
+                LogLevel::Debug => log_debug_cpp(line.as_ptr()),
+                LogLevel::Info => log_info_cpp(line.as_ptr()),
+                LogLevel::Warn => log_warn_cpp(line.as_ptr()),
+                LogLevel::Error => log_error_cpp(line.as_ptr()),
+                LogLevel::Off => {}
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static DEFOLD_LOGGER: DefoldLogger = DefoldLogger;
+
+/// Routes the `log` facade to the Defold console. Only the first call installs the
+/// logger, later ones keep it.
+pub fn install_logger() {
+    let _ = log::set_logger(&DEFOLD_LOGGER);
+    update_log_max_level();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json_vec_grows_up_to_max_len() {
+        let values = [1u8; 300];
+
+        let json = to_json_vec(&values[..], 1024).unwrap();
+
+        assert_eq!(json.len(), 2 + 300 * 2 - 1);
+        assert_eq!(&json[..4], b"[1,1");
+        assert_eq!(
+            to_json_vec(&values[..], json.len() - 1),
+            Err(PayloadError::TooLarge)
+        );
+    }
+
+    #[derive(Serialize)]
+    struct UnitState {
+        id: u32,
+        hp: i16,
+        alive: bool,
+        position: [f32; 2],
+    }
+
+    #[test]
+    fn test_binary_payload_layout() {
+        let state = UnitState {
+            id: 300,
+            hp: -2,
+            alive: true,
+            position: [1.0, -0.5],
+        };
+
+        let bytes = payload_to_binary(&state).unwrap();
+
+        assert_eq!(
+            bytes,
+            [
+                0xac, 0x02, // id
+                0x03, // hp
+                0x01, // alive
+                0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xbf, // position
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_line_tags_module_and_formats_arguments() {
+        let line = format_log_line(module_path!(), format_args!("cell {:?} blocked", [3, -1]));
+
+        assert_eq!(
+            line.as_str(),
+            "[rust_defold_try::defold::tests] cell [3, -1] blocked"
+        );
+    }
+
+    #[test]
+    fn test_log_line_cuts_long_messages() {
+        let long = "é".repeat(LOG_LINE_CAPACITY);
+
+        let line = format_log_line("m", format_args!("{}", long));
+
+        assert!(line.len() < LOG_LINE_CAPACITY);
+        assert!(line.as_str().starts_with("[m] éé"));
+    }
+
+    #[test]
+    fn test_log_levels_are_ordered() {
+        assert!(LogLevel::Debug < LogLevel::Info);
+        assert!(LogLevel::Warn < LogLevel::Error);
+        assert!(LogLevel::Error < LogLevel::Off);
+    }
+
+    #[test]
+    fn test_target_filters_match_longest_module_prefix() {
+        let filters = BTreeMap::from([
+            (String::from("bevy_ecs"), LogLevel::Error),
+            (String::from("bevy_ecs::world"), LogLevel::Debug),
+        ]);
+
+        assert_eq!(
+            min_level_for_target(&filters, "bevy_ecs::schedule"),
+            LogLevel::Error
+        );
+        assert_eq!(
+            min_level_for_target(&filters, "bevy_ecs::world::entity_ref"),
+            LogLevel::Debug
+        );
+        assert_eq!(
+            min_level_for_target(&filters, "bevy_ecs_macros"),
+            LogLevel::from_u8(MIN_LOG_LEVEL.load(Ordering::Relaxed))
+        );
+    }
+
+    #[test]
+    fn test_log_record_includes_target_and_module() {
+        let line = format_log_record(
+            &log::Record::builder()
+                .args(format_args!("spawned {}", 3))
+                .target("particles")
+                .module_path(Some("rust_defold_try::particles"))
+                .build(),
+        );
+
+        assert_eq!(
+            line.as_str(),
+            "[particles] (rust_defold_try::particles) spawned 3"
+        );
     }
 }
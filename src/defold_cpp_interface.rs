@@ -1,8 +1,10 @@
+use bevy_math::{Quat, Vec3, Vec4};
 use bevy_transform::components::Transform;
 
 include!("bindings.rs");
 
 #[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct GoTransform {
     pub rotation: [f32; 4],
     pub translation: [f32; 3],
@@ -19,10 +21,188 @@ impl From<Transform> for GoTransform {
     }
 }
 
+impl From<GoTransform> for Transform {
+    fn from(value: GoTransform) -> Self {
+        Transform {
+            translation: Vec3::from_array(value.translation),
+            rotation: Quat::from_array(value.rotation),
+            scale: Vec3::from_array(value.scale),
+        }
+    }
+}
+
+/// `dmMessage::URL` in hashed form, resolved once by C++ instead of parsing a
+/// string on every call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashedUrl {
+    /// Hash of the socket name.
+    pub socket: dmhash_t,
+    pub path: dmhash_t,
+    pub fragment: dmhash_t,
+}
+
 unsafe extern "C" {
     pub(crate) unsafe fn set_go_transform_cpp(url: *const u8, go_transform: GoTransform);
 }
 
+unsafe extern "C" {
+    pub(crate) unsafe fn set_go_transform_hashed_cpp(url: HashedUrl, go_transform: GoTransform);
+}
+
+/// Receiver of a batched call, hashed when the URL came from the engine.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashedOrStringUrl {
+    Hashed(HashedUrl),
+    /// Null terminated string.
+    String(*const u8),
+}
+
+pub const GO_TRANSFORM_TRANSLATION: u8 = 1 << 0;
+pub const GO_TRANSFORM_ROTATION: u8 = 1 << 1;
+pub const GO_TRANSFORM_SCALE: u8 = 1 << 2;
+pub const GO_TRANSFORM_ALL: u8 =
+    GO_TRANSFORM_TRANSLATION | GO_TRANSFORM_ROTATION | GO_TRANSFORM_SCALE;
+
+/// One game object of `set_go_transforms_batch_cpp`, only the `GO_TRANSFORM_*`
+/// channels set in `channels` are written.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoTransformBatchEntry {
+    pub url: HashedOrStringUrl,
+    pub transform: GoTransform,
+    pub channels: u8,
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn set_go_transforms_batch_cpp(
+        entries: *const GoTransformBatchEntry,
+        count: usize,
+    );
+}
+
+/// Target value of `animate_property_cpp`, matching the type of the property.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimValue {
+    Number(f32),
+    Vector3([f32; 3]),
+    Vector4([f32; 4]),
+    Quat([f32; 4]),
+}
+
+impl From<f32> for AnimValue {
+    fn from(value: f32) -> Self {
+        AnimValue::Number(value)
+    }
+}
+
+impl From<Vec3> for AnimValue {
+    fn from(value: Vec3) -> Self {
+        AnimValue::Vector3(value.to_array())
+    }
+}
+
+impl From<Vec4> for AnimValue {
+    fn from(value: Vec4) -> Self {
+        AnimValue::Vector4(value.to_array())
+    }
+}
+
+impl From<Quat> for AnimValue {
+    fn from(value: Quat) -> Self {
+        AnimValue::Quat(value.to_array())
+    }
+}
+
+/// `go.EASING_*` curves.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+    InOutSine,
+    InBack,
+    OutBack,
+    InOutBack,
+    InBounce,
+    OutBounce,
+    InOutBounce,
+    InElastic,
+    OutElastic,
+    InOutElastic,
+}
+
+/// `go.PLAYBACK_*` modes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Playback {
+    #[default]
+    OnceForward,
+    OnceBackward,
+    OncePingpong,
+    LoopForward,
+    LoopBackward,
+    LoopPingpong,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimateResultCpp {
+    Success,
+    NotFound,
+    /// The game object has no such property or its type differs from the value.
+    InvalidProperty,
+}
+
+unsafe extern "C" {
+    /// Starts `go.animate` on the property. When a `ONCE_*` animation completes, C++
+    /// sends an `animation_finished` message to the app through `app_on_message`,
+    /// with the game object as sender and `{"id":animation_id,"property":property}`
+    /// as payload, both written as exact integers.
+    pub(crate) unsafe fn animate_property_cpp(
+        url: HashedOrStringUrl,
+        property: dmhash_t,
+        to: AnimValue,
+        easing: Easing,
+        duration: f32,
+        delay: f32,
+        playback: Playback,
+        animation_id: u64,
+    ) -> AnimateResultCpp;
+}
+
+#[repr(C)]
+pub enum GetGoTransformResultCpp {
+    Success { transform: GoTransform },
+    NotFound,
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn get_go_transform_cpp(url: *const u8) -> GetGoTransformResultCpp;
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn get_go_transform_hashed_cpp(url: HashedUrl) -> GetGoTransformResultCpp;
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn set_go_position_cpp(url: *const u8, x: f32, y: f32, z: f32);
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn set_go_rotation_cpp(url: *const u8, x: f32, y: f32, z: f32, w: f32);
+}
+
 unsafe extern "C" {
     pub(crate) unsafe fn post_message_cpp(
         url: *const u8,
@@ -32,9 +212,39 @@ unsafe extern "C" {
     );
 }
 
+unsafe extern "C" {
+    /// Same as `post_message_cpp` with a binary payload, see `post_message_binary_to_view`.
+    pub(crate) unsafe fn post_message_binary_cpp(
+        url: *const u8,
+        message_name: *const u8,
+        message_data: *const u8,
+        message_data_len: usize,
+    );
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn post_message_hashed_cpp(
+        url: HashedUrl,
+        message_id: dmhash_t,
+        message_data: *const u8,
+        message_data_len: usize,
+    );
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn post_message_binary_hashed_cpp(
+        url: HashedUrl,
+        message_id: dmhash_t,
+        message_data: *const u8,
+        message_data_len: usize,
+    );
+}
+
 #[repr(C)]
 pub enum CreateViewResultCpp {
+    /// `url_raw_ptr` is the string form of `url`, kept for logging.
     Success {
+        url: HashedUrl,
         url_raw_ptr: *const u8,
         url_len: usize,
     },
@@ -56,6 +266,67 @@ unsafe extern "C" {
     ) -> CreateViewResultCpp;
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteViewResultCpp {
+    Success,
+    /// No game object at the URL, it was already deleted.
+    NotFound,
+    CallbackCallError,
+    InvalidLuaContext,
+}
+
+unsafe extern "C" {
+    /// `recursive` also deletes the children of the game object.
+    pub(crate) unsafe fn delete_view_cpp(url: *const u8, recursive: bool) -> DeleteViewResultCpp;
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn delete_view_hashed_cpp(
+        url: HashedUrl,
+        recursive: bool,
+    ) -> DeleteViewResultCpp;
+}
+
+/// Record of the batched command buffer, see `app_take_commands`. Pointers are null
+/// terminated strings unless paired with a `_len` field. URLs are hashed whenever
+/// the engine reported their hashed form.
+#[repr(C)]
+#[derive(Debug)]
+pub enum DefoldCommandCpp {
+    SetTransform {
+        url: HashedOrStringUrl,
+        transform: GoTransform,
+    },
+    /// `message_id` is `hash(message_name)`, the name is only kept for logging.
+    PostMessage {
+        url: HashedOrStringUrl,
+        message_id: dmhash_t,
+        message_name: *const u8,
+        message_data: *const u8,
+        message_data_len: usize,
+    },
+    CreateView {
+        request_id: u64,
+        view_factory_id: dmhash_t,
+        transform: GoTransform,
+        properties_data: *const u8,
+        properties_data_len: usize,
+    },
+    DeleteView {
+        url: HashedOrStringUrl,
+        recursive: bool,
+    },
+    /// `PostMessage` with a binary payload.
+    PostBinaryMessage {
+        url: HashedOrStringUrl,
+        message_id: dmhash_t,
+        message_name: *const u8,
+        message_data: *const u8,
+        message_data_len: usize,
+    },
+}
+
 unsafe extern "C" {
     pub(crate) unsafe fn log_info_cpp(message_name: *const u8);
 }
@@ -63,3 +334,25 @@ unsafe extern "C" {
 unsafe extern "C" {
     pub(crate) unsafe fn log_error_cpp(message_name: *const u8);
 }
+
+unsafe extern "C" {
+    pub(crate) unsafe fn log_warn_cpp(message_name: *const u8);
+}
+
+unsafe extern "C" {
+    pub(crate) unsafe fn log_debug_cpp(message_name: *const u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_go_transform_round_trip() {
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0)
+            .with_rotation(Quat::from_rotation_z(0.5))
+            .with_scale(Vec3::new(2.0, 2.0, 1.0));
+
+        assert_eq!(Transform::from(GoTransform::from(transform)), transform);
+    }
+}
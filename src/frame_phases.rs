@@ -0,0 +1,271 @@
+use core::time::Duration;
+
+use bevy_app::{FixedMain, MainScheduleOrder, RunFixedMainLoop, Update};
+use bevy_ecs::{
+    schedule::{InternedScheduleLabel, ScheduleLabel},
+    system::Resource,
+    world::{Mut, World},
+};
+use bevy_time::{Fixed, Time, Virtual};
+
+use crate::{
+    app_registry::AppStatus,
+    host_time::{TimeMode, expect_time_mode, set_host_delta},
+};
+
+/// How a host driven app is advanced. Mixing whole frames with phases would run the
+/// startup schedules twice, so the first call decides for the lifetime of the app.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FrameDriver {
+    /// `update_app_with_dt` runs the whole `Main` schedule.
+    WholeFrame,
+    /// `app_fixed_update`, `app_update` and `app_late_update` run a part of it each.
+    Phases { started: bool },
+}
+
+pub(crate) fn claim_whole_frame_driver(world: &mut World) -> Result<(), AppStatus> {
+    match world.get_resource::<FrameDriver>() {
+        None => {
+            world.insert_resource(FrameDriver::WholeFrame);
+            Ok(())
+        }
+        Some(FrameDriver::WholeFrame) => Ok(()),
+        Some(FrameDriver::Phases { .. }) => Err(AppStatus::WrongTimeMode),
+    }
+}
+
+/// Checks the app may be driven by phases and runs the startup schedules on the first call.
+fn begin_phase(world: &mut World) -> Result<(), AppStatus> {
+    expect_time_mode(world, TimeMode::HostDriven)?;
+    let started = match world.get_resource::<FrameDriver>() {
+        None => false,
+        Some(FrameDriver::Phases { started }) => *started,
+        Some(FrameDriver::WholeFrame) => return Err(AppStatus::WrongTimeMode),
+    };
+    if !started {
+        world.insert_resource(FrameDriver::Phases { started: true });
+        world.resource_scope(|world, order: Mut<MainScheduleOrder>| {
+            for &label in &order.startup_labels {
+                let _ = world.try_run_schedule(label);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Runs the main schedules before (`up_to_update == true`) or after `Update`, skipping
+/// `RunFixedMainLoop` because the fixed step is driven by Defold.
+fn run_main_schedules(world: &mut World, up_to_update: bool) {
+    world.resource_scope(|world, order: Mut<MainScheduleOrder>| {
+        let update_label: InternedScheduleLabel = Update.intern();
+        let fixed_loop_label: InternedScheduleLabel = RunFixedMainLoop.intern();
+        let split = order
+            .labels
+            .iter()
+            .position(|label| *label == update_label)
+            .map_or(order.labels.len(), |index| index + 1);
+        let labels = if up_to_update {
+            &order.labels[..split]
+        } else {
+            &order.labels[split..]
+        };
+        for &label in labels.iter().filter(|label| **label != fixed_loop_label) {
+            let _ = world.try_run_schedule(label);
+        }
+    });
+}
+
+/// Defold `fixed_update`: `fixed_dt_seconds` becomes the fixed timestep and
+/// `FixedMain` runs once, see [`run_fixed_step_phase`].
+pub(crate) fn run_fixed_update_phase(
+    world: &mut World,
+    fixed_dt_seconds: f32,
+) -> Result<(), AppStatus> {
+    let fixed_dt = Duration::try_from_secs_f32(fixed_dt_seconds)
+        .ok()
+        .filter(|fixed_dt| !fixed_dt.is_zero())
+        .ok_or(AppStatus::InvalidArgument)?;
+    begin_phase(world)?;
+    world.resource_mut::<Time<Fixed>>().set_timestep(fixed_dt);
+    run_fixed_step(world)
+}
+
+/// Runs `FixedMain` once with the timestep the host set last. Like Bevy's own fixed
+/// loop it follows `Virtual` time: nothing runs while paused and the step is scaled
+/// by the time scale.
+pub(crate) fn run_fixed_step_phase(world: &mut World) -> Result<(), AppStatus> {
+    begin_phase(world)?;
+    run_fixed_step(world)
+}
+
+fn run_fixed_step(world: &mut World) -> Result<(), AppStatus> {
+    // `effective_speed` only catches up on the next update, read the settings directly.
+    let virtual_time = world.resource::<Time<Virtual>>();
+    let speed = if virtual_time.is_paused() {
+        0.0
+    } else {
+        virtual_time.relative_speed()
+    };
+    if speed == 0.0 {
+        return Ok(());
+    }
+    let mut fixed_time = world.resource_mut::<Time<Fixed>>();
+    let delta = Duration::try_from_secs_f32(fixed_time.timestep().as_secs_f32() * speed)
+        .map_err(|_| AppStatus::InvalidArgument)?;
+    fixed_time.advance_by(delta);
+    let fixed_time = fixed_time.as_generic();
+
+    *world.resource_mut::<Time>() = fixed_time;
+    let _ = world.try_run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+    Ok(())
+}
+
+/// Defold `update`: advances `Time` by `dt_seconds` and runs the schedules up to `Update`.
+pub(crate) fn run_update_phase(world: &mut World, dt_seconds: f32) -> Result<(), AppStatus> {
+    begin_phase(world)?;
+    set_host_delta(world, dt_seconds)?;
+    run_main_schedules(world, true);
+    Ok(())
+}
+
+/// Defold `late_update`: runs `PostUpdate` and the rest of the frame, transform changes
+/// made by physics in `fixed_update` are flushed here.
+pub(crate) fn run_late_update_phase(world: &mut World) -> Result<(), AppStatus> {
+    begin_phase(world)?;
+    run_main_schedules(world, false);
+    world.clear_trackers();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use bevy_app::{App, FixedUpdate, PostUpdate, Startup};
+    use bevy_ecs::system::{Res, ResMut};
+    use bevy_time::TimePlugin;
+
+    use super::*;
+    use crate::host_time::configure_time;
+
+    #[derive(Resource, Default)]
+    struct Calls(Vec<(&'static str, f32)>);
+
+    fn host_driven_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .init_resource::<Calls>()
+            .add_systems(Startup, |mut calls: ResMut<Calls>| {
+                calls.0.push(("startup", 0.0))
+            })
+            .add_systems(FixedUpdate, |time: Res<Time>, mut calls: ResMut<Calls>| {
+                calls.0.push(("fixed", time.delta_secs()))
+            })
+            .add_systems(Update, |time: Res<Time>, mut calls: ResMut<Calls>| {
+                calls.0.push(("update", time.delta_secs()))
+            })
+            .add_systems(PostUpdate, |mut calls: ResMut<Calls>| {
+                calls.0.push(("post_update", 0.0))
+            });
+        configure_time(&mut app, TimeMode::HostDriven);
+        app
+    }
+
+    #[test]
+    fn test_phases_run_their_schedules_only() {
+        let mut app = host_driven_app();
+        let world = app.world_mut();
+
+        run_fixed_update_phase(world, 0.5).unwrap();
+        run_fixed_update_phase(world, 0.5).unwrap();
+        run_update_phase(world, 0.25).unwrap();
+        run_late_update_phase(world).unwrap();
+        run_update_phase(world, 0.125).unwrap();
+
+        assert_eq!(
+            world.resource::<Calls>().0,
+            [
+                ("startup", 0.0),
+                ("fixed", 0.5),
+                ("fixed", 0.5),
+                ("update", 0.25),
+                ("post_update", 0.0),
+                ("update", 0.125),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixed_phase_restores_virtual_time() {
+        let mut app = host_driven_app();
+        let world = app.world_mut();
+
+        run_update_phase(world, 0.25).unwrap();
+        run_fixed_update_phase(world, 0.5).unwrap();
+
+        assert_eq!(world.resource::<Time>().delta_secs(), 0.25);
+    }
+
+    #[test]
+    fn test_fixed_phase_follows_virtual_time() {
+        let mut app = host_driven_app();
+        let world = app.world_mut();
+
+        world
+            .resource_mut::<Time<Virtual>>()
+            .set_relative_speed(0.5);
+        run_fixed_update_phase(world, 0.5).unwrap();
+        world.resource_mut::<Time<Virtual>>().pause();
+        run_fixed_update_phase(world, 0.5).unwrap();
+
+        assert_eq!(
+            world.resource::<Calls>().0,
+            [("startup", 0.0), ("fixed", 0.25)]
+        );
+        assert_eq!(world.resource::<Time<Fixed>>().elapsed_secs(), 0.25);
+        assert_eq!(
+            world.resource::<Time<Fixed>>().timestep().as_secs_f32(),
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_phases_and_whole_frames_not_mixed() {
+        let mut app = host_driven_app();
+        let world = app.world_mut();
+
+        run_update_phase(world, 0.1).unwrap();
+
+        assert_eq!(
+            claim_whole_frame_driver(world),
+            Err(AppStatus::WrongTimeMode)
+        );
+    }
+
+    #[test]
+    fn test_invalid_fixed_dt_rejected() {
+        let mut app = host_driven_app();
+
+        assert_eq!(
+            run_fixed_update_phase(app.world_mut(), 0.0),
+            Err(AppStatus::InvalidArgument)
+        );
+        assert_eq!(
+            run_fixed_update_phase(app.world_mut(), 1e30),
+            Err(AppStatus::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_phases_rejected_in_wall_clock_mode() {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin);
+        configure_time(&mut app, TimeMode::WallClock);
+
+        assert_eq!(
+            run_late_update_phase(app.world_mut()),
+            Err(AppStatus::WrongTimeMode)
+        );
+    }
+}
@@ -1,5 +1,6 @@
+use alloc::vec::Vec;
 use core::fmt::Debug;
-use graph_key::{EdgeData, EdgeKey, VertexPointAndData};
+use graph_key::{EdgeData, EdgeKey, Vertex3Key, VertexPointAndData};
 use indexmap_nostd::IndexMap;
 use indexmap_nostd::map::Entry;
 
@@ -162,6 +163,37 @@ impl<DataType: Clone + Eq + Debug> Graph<DataType> {
     pub fn get_edge_two_way(&self, edge_key: EdgeKey) -> Option<&EdgeData<DataType>> {
         self.edges_data.get(&edge_key)
     }
+
+    /// Every stored direction as its own edge, inserting them back with
+    /// `insert_edge_one_way` rebuilds the same graph.
+    pub(crate) fn one_way_edges(&self) -> Vec<(EdgeKey, &DataType)> {
+        let directed = |edge_key: &EdgeKey, from: Vertex3Key| {
+            if edge_key.from == from {
+                edge_key.clone()
+            } else {
+                EdgeKey {
+                    from,
+                    to: edge_key.from,
+                }
+            }
+        };
+
+        let mut edges = Vec::new();
+        for (edge_key, edge_data) in self.edges_data.iter() {
+            match edge_data {
+                EdgeData::One(stored) => edges.push((directed(edge_key, stored.0), &stored.1)),
+                EdgeData::IdencticalTwo(stored_data) => {
+                    edges.push((directed(edge_key, edge_key.from), stored_data));
+                    edges.push((directed(edge_key, edge_key.to), stored_data));
+                }
+                EdgeData::NonIdencticalTwo(first, second) => {
+                    edges.push((directed(edge_key, first.0), &first.1));
+                    edges.push((directed(edge_key, second.0), &second.1));
+                }
+            }
+        }
+        edges
+    }
 }
 
 #[cfg(test)]
@@ -469,4 +501,25 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn one_way_edges_rebuild_same_graph() {
+        let a = I16Vec3::new(0, 0, 0);
+        let b = I16Vec3::new(1, 0, 0);
+        let c = I16Vec3::new(0, 1, 0);
+        let mut graph = Graph::new();
+        graph.insert_edge_one_way(EdgeKey::new(a, b).unwrap(), 1);
+        graph.insert_edge_two_way(EdgeKey::new(a, c).unwrap(), 2);
+        graph.insert_edge_one_way(EdgeKey::new(b, c).unwrap(), 3);
+        graph.insert_edge_one_way(EdgeKey::new(c, b).unwrap(), 4);
+
+        let edges = graph.one_way_edges();
+        assert_eq!(edges.len(), 5);
+
+        let mut rebuilt = Graph::new();
+        for (edge_key, data) in edges {
+            rebuilt.insert_edge_one_way(edge_key, *data);
+        }
+        assert_eq!(rebuilt, graph);
+    }
 }
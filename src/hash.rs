@@ -0,0 +1,142 @@
+use core::fmt;
+
+use crate::defold_cpp_interface::dmhash_t;
+
+const M: u64 = 0xc6a4a7935bd1e995;
+const R: u32 = 47;
+
+const fn mmix(h: u64, mut k: u64) -> u64 {
+    k = k.wrapping_mul(M);
+    k ^= k >> R;
+    k = k.wrapping_mul(M);
+    h.wrapping_mul(M) ^ k
+}
+
+/// Defold's 64-bit hash, the value `hash(name)` gives on the Lua side. This is the
+/// MurmurHash2A construction of `dmHashBuffer64` (seed `0`, bytes read little
+/// endian): the length is mixed in last so the engine can also hash incrementally.
+pub const fn hash_bytes(bytes: &[u8]) -> dmhash_t {
+    let len = bytes.len();
+    let mut h = 0;
+
+    let mut i = 0;
+    while i + 8 <= len {
+        let k = u64::from_le_bytes([
+            bytes[i],
+            bytes[i + 1],
+            bytes[i + 2],
+            bytes[i + 3],
+            bytes[i + 4],
+            bytes[i + 5],
+            bytes[i + 6],
+            bytes[i + 7],
+        ]);
+        h = mmix(h, k);
+        i += 8;
+    }
+
+    let mut tail = 0;
+    let mut j = 0;
+    while i + j < len {
+        tail ^= (bytes[i + j] as u64) << (8 * j);
+        j += 1;
+    }
+    h = mmix(h, tail);
+    h = mmix(h, len as u64);
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+pub const fn hash_str(name: &str) -> dmhash_t {
+    hash_bytes(name.as_bytes())
+}
+
+/// Hash of a string literal computed at compile time. Patterns can't call it
+/// directly, bind it to a `const` to `match` on it:
+/// `const JUMP: dmhash_t = dmhash!("jump");`
+#[macro_export]
+macro_rules! dmhash {
+    ($name:expr) => {
+        const { $crate::hash::hash_str($name) }
+    };
+}
+
+/// Names hashed at runtime, so debug builds can print hashes without the engine.
+#[cfg(debug_assertions)]
+static REVERSE_HASHES: spin::Mutex<alloc::collections::BTreeMap<dmhash_t, alloc::string::String>> =
+    spin::Mutex::new(alloc::collections::BTreeMap::new());
+
+/// Hashes `name` at runtime and remembers it for [`DisplayHash`] in debug builds.
+pub fn hash_string(name: &str) -> dmhash_t {
+    let hash = hash_str(name);
+    #[cfg(debug_assertions)]
+    REVERSE_HASHES
+        .lock()
+        .entry(hash)
+        .or_insert_with(|| name.into());
+    hash
+}
+
+/// Calls `f` with the name `hash` was computed from, if [`hash_string`] has seen it.
+/// Always `None` in release builds.
+pub fn with_reverse_hash<R>(hash: dmhash_t, f: impl FnOnce(&str) -> R) -> Option<R> {
+    #[cfg(debug_assertions)]
+    {
+        REVERSE_HASHES.lock().get(&hash).map(|name| f(name))
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = (hash, f);
+        None
+    }
+}
+
+/// Formats a hash as its name when known, otherwise like Defold does: `hash: [0x...]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayHash(pub dmhash_t);
+
+impl fmt::Display for DisplayHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        with_reverse_hash(self.0, |name| f.write_str(name))
+            .unwrap_or_else(|| write!(f, "hash: [{:#018x}]", self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMP: dmhash_t = dmhash!("jump");
+
+    #[test]
+    fn test_hash_known_values() {
+        // `print(hash("jump"))` in the engine gives `hash: [0xd824d103c3431e62]`.
+        assert_eq!(hash_str(""), 0);
+        assert_eq!(hash_str("jump"), 0xd824d103c3431e62);
+        // Longer than one 8 byte block, so the block loop and the tail both run.
+        assert_eq!(hash_str("main:/units/go#script"), 0x2e5adb389522fe3e);
+    }
+
+    #[test]
+    fn test_macro_is_usable_in_patterns_through_const() {
+        let action = hash_string("jump");
+
+        let matched = match action {
+            JUMP => "jump",
+            _ => "other",
+        };
+
+        assert_eq!(matched, "jump");
+    }
+
+    #[test]
+    fn test_display_uses_reverse_table() {
+        let hash = hash_string("touch");
+
+        assert_eq!(DisplayHash(hash).to_string(), "touch");
+        assert_eq!(DisplayHash(0x2a).to_string(), "hash: [0x000000000000002a]");
+    }
+}
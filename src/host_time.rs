@@ -0,0 +1,120 @@
+use core::time::Duration;
+
+use bevy_app::App;
+use bevy_ecs::{system::Resource, world::World};
+use bevy_time::{Real, Time, TimeUpdateStrategy, Virtual};
+use serde::Deserialize;
+
+use crate::app_registry::AppStatus;
+
+/// Source of the app clock, chosen once when the world is created.
+#[repr(C)]
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeMode {
+    /// `TimePlugin` measures wall-clock time itself, the app is advanced with `update_app`.
+    WallClock,
+    /// Time only moves by the `dt` Defold passes to `update_app_with_dt`.
+    HostDriven,
+}
+
+/// Must run after `TimePlugin` was added.
+pub(crate) fn configure_time(app: &mut App, time_mode: TimeMode) {
+    app.insert_resource(time_mode);
+    if time_mode == TimeMode::HostDriven {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        let world = app.world_mut();
+        // The very first real time update only records a starting point, do it now so
+        // the first dt coming from Defold is not swallowed.
+        world
+            .resource_mut::<Time<Real>>()
+            .update_with_duration(Duration::ZERO);
+        // Defold already decided how long the frame was, long frames must not be clamped.
+        world
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(Duration::MAX);
+    }
+}
+
+pub(crate) fn expect_time_mode(world: &World, expected: TimeMode) -> Result<(), AppStatus> {
+    match world.get_resource::<TimeMode>() {
+        Some(time_mode) if *time_mode == expected => Ok(()),
+        _ => Err(AppStatus::WrongTimeMode),
+    }
+}
+
+/// Sets the duration the next `Time` update will advance by.
+pub(crate) fn set_host_delta(world: &mut World, dt_seconds: f32) -> Result<(), AppStatus> {
+    expect_time_mode(world, TimeMode::HostDriven)?;
+    let dt = Duration::try_from_secs_f32(dt_seconds).map_err(|_| AppStatus::InvalidArgument)?;
+    world.insert_resource(TimeUpdateStrategy::ManualDuration(dt));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_time::TimePlugin;
+
+    use super::*;
+
+    fn host_driven_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin);
+        configure_time(&mut app, TimeMode::HostDriven);
+        app
+    }
+
+    #[test]
+    fn test_host_delta_advances_time() {
+        let mut app = host_driven_app();
+
+        for _ in 0..4 {
+            set_host_delta(app.world_mut(), 0.25).unwrap();
+            app.update();
+        }
+
+        let time = app.world().resource::<Time>();
+        assert_eq!(time.delta_secs(), 0.25);
+        assert_eq!(time.elapsed_secs(), 1.0);
+    }
+
+    #[test]
+    fn test_long_host_delta_not_clamped() {
+        let mut app = host_driven_app();
+
+        set_host_delta(app.world_mut(), 2.0).unwrap();
+        app.update();
+
+        assert_eq!(app.world().resource::<Time>().delta_secs(), 2.0);
+    }
+
+    #[test]
+    fn test_invalid_host_delta_rejected() {
+        let mut app = host_driven_app();
+
+        assert_eq!(
+            set_host_delta(app.world_mut(), -1.0),
+            Err(AppStatus::InvalidArgument)
+        );
+        assert_eq!(
+            set_host_delta(app.world_mut(), f32::NAN),
+            Err(AppStatus::InvalidArgument)
+        );
+        assert_eq!(
+            set_host_delta(app.world_mut(), 1e30),
+            Err(AppStatus::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_host_delta_rejected_in_wall_clock_mode() {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin);
+        configure_time(&mut app, TimeMode::WallClock);
+
+        assert_eq!(
+            set_host_delta(app.world_mut(), 0.1),
+            Err(AppStatus::WrongTimeMode)
+        );
+    }
+}
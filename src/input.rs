@@ -0,0 +1,194 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use bevy_app::{App, Last, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_math::Vec2;
+
+use crate::defold_cpp_interface::dmhash_t;
+
+/// Input state fed from Defold `on_input`, with the same semantics as Bevy `ButtonInput`:
+/// `just_pressed` and `just_released` stay set for the whole frame the action arrived in.
+#[derive(Resource, Default, Debug)]
+pub struct DefoldInput {
+    pressed: BTreeSet<dmhash_t>,
+    just_pressed: BTreeSet<dmhash_t>,
+    just_released: BTreeSet<dmhash_t>,
+    values: BTreeMap<dmhash_t, f32>,
+    pointer_position: Vec2,
+    pointer_screen_position: Vec2,
+}
+
+impl DefoldInput {
+    pub fn pressed(&self, action_id: dmhash_t) -> bool {
+        self.pressed.contains(&action_id)
+    }
+
+    pub fn just_pressed(&self, action_id: dmhash_t) -> bool {
+        self.just_pressed.contains(&action_id)
+    }
+
+    pub fn just_released(&self, action_id: dmhash_t) -> bool {
+        self.just_released.contains(&action_id)
+    }
+
+    pub fn get_pressed(&self) -> impl Iterator<Item = &dmhash_t> {
+        self.pressed.iter()
+    }
+
+    /// Last `value` Defold sent for the action (analog sticks, triggers, scroll).
+    pub fn value(&self, action_id: dmhash_t) -> f32 {
+        self.values.get(&action_id).copied().unwrap_or(0.0)
+    }
+
+    /// Pointer position in world space (`action.x`, `action.y`).
+    pub fn pointer_position(&self) -> Vec2 {
+        self.pointer_position
+    }
+
+    /// Pointer position in screen space (`action.screen_x`, `action.screen_y`).
+    pub fn pointer_screen_position(&self) -> Vec2 {
+        self.pointer_screen_position
+    }
+
+    fn press(&mut self, action_id: dmhash_t) {
+        if self.pressed.insert(action_id) {
+            self.just_pressed.insert(action_id);
+        }
+    }
+
+    fn release(&mut self, action_id: dmhash_t) {
+        if self.pressed.remove(&action_id) {
+            self.just_released.insert(action_id);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+/// One `on_input` call as Defold delivers it.
+pub(crate) struct DefoldInputAction {
+    /// `0` for pointer movement without an action.
+    pub(crate) action_id: dmhash_t,
+    pub(crate) pressed: bool,
+    pub(crate) released: bool,
+    pub(crate) value: f32,
+    pub(crate) position: Vec2,
+    pub(crate) screen_position: Vec2,
+}
+
+pub(crate) fn apply_defold_input(world: &mut World, action: DefoldInputAction) {
+    let mut input = world.get_resource_or_insert_with(DefoldInput::default);
+    input.pointer_position = action.position;
+    input.pointer_screen_position = action.screen_position;
+
+    if action.action_id == 0 {
+        return;
+    }
+    input.values.insert(action.action_id, action.value);
+    if action.pressed {
+        input.press(action.action_id);
+    }
+    if action.released {
+        input.release(action.action_id);
+    }
+}
+
+fn clear_defold_input(mut input: ResMut<DefoldInput>) {
+    input.clear();
+}
+
+pub struct DefoldInputPlugin;
+impl Plugin for DefoldInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DefoldInput>()
+            .add_systems(Last, clear_defold_input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use bevy_app::Update;
+
+    use super::*;
+
+    const JUMP: dmhash_t = 17;
+
+    #[derive(Resource, Default)]
+    struct Seen(Vec<(bool, bool, bool)>);
+
+    fn record_jump(input: Res<DefoldInput>, mut seen: ResMut<Seen>) {
+        seen.0.push((
+            input.pressed(JUMP),
+            input.just_pressed(JUMP),
+            input.just_released(JUMP),
+        ));
+    }
+
+    fn action(action_id: dmhash_t, pressed: bool, released: bool) -> DefoldInputAction {
+        DefoldInputAction {
+            action_id,
+            pressed,
+            released,
+            value: if pressed { 1.0 } else { 0.0 },
+            position: Vec2::new(10.0, 20.0),
+            screen_position: Vec2::new(30.0, 40.0),
+        }
+    }
+
+    fn input_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(DefoldInputPlugin)
+            .init_resource::<Seen>()
+            .add_systems(Update, record_jump);
+        app
+    }
+
+    #[test]
+    fn test_press_hold_release() {
+        let mut app = input_app();
+
+        apply_defold_input(app.world_mut(), action(JUMP, true, false));
+        app.update();
+        apply_defold_input(app.world_mut(), action(JUMP, false, false));
+        app.update();
+        apply_defold_input(app.world_mut(), action(JUMP, false, true));
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world().resource::<Seen>().0,
+            [
+                (true, true, false),
+                (true, false, false),
+                (false, false, true),
+                (false, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pointer_movement_updates_position_only() {
+        let mut app = input_app();
+
+        apply_defold_input(app.world_mut(), action(0, false, false));
+
+        let input = app.world().resource::<DefoldInput>();
+        assert_eq!(input.pointer_position(), Vec2::new(10.0, 20.0));
+        assert_eq!(input.pointer_screen_position(), Vec2::new(30.0, 40.0));
+        assert_eq!(input.get_pressed().count(), 0);
+    }
+
+    #[test]
+    fn test_release_without_press_ignored() {
+        let mut app = input_app();
+
+        apply_defold_input(app.world_mut(), action(JUMP, false, true));
+
+        assert!(!app.world().resource::<DefoldInput>().just_released(JUMP));
+    }
+}
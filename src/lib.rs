@@ -5,12 +5,31 @@ extern crate alloc;
 #[cfg(test)]
 extern crate std;
 
+pub mod animation;
+pub mod app_registry;
 pub mod bevy_app_config;
 pub mod bevy_cpp_interface;
+#[cfg(feature = "board")]
 pub mod board;
+pub mod command_buffer;
 pub mod defold;
 pub mod defold_cpp_interface;
+pub mod frame_phases;
+#[cfg(feature = "graph")]
 pub mod graph;
+pub mod hash;
+pub mod host_time;
 pub mod idir2;
+pub mod input;
+pub mod messages;
+#[cfg(feature = "particles")]
 pub mod particles;
+pub mod rng;
+pub mod rpc;
+pub mod simulation;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod transform_sync;
+pub mod url;
+pub mod view;
 pub mod world_sides;
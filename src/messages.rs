@@ -0,0 +1,207 @@
+use alloc::vec::Vec;
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::prelude::*;
+use no_std_strings::ztr32;
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::{
+    defold::{self, MessageName, MessagePayload, PayloadError, URL},
+    defold_cpp_interface::dmhash_t,
+    hash::hash_str,
+};
+
+/// Message with a fixed name, sent and received with the same payload type.
+/// The encoding of outgoing messages comes from [`MessagePayload`].
+pub trait DefoldMessage: MessagePayload + DeserializeOwned {
+    /// Message id as written on the Lua side, at most 31 bytes.
+    const NAME: &'static str;
+}
+
+/// `hash(M::NAME)`, compared with the id of incoming messages. Always derived from
+/// the name so senders posting by name and by hash agree.
+pub const fn message_hash<M: DefoldMessage>() -> dmhash_t {
+    hash_str(M::NAME)
+}
+
+pub(crate) fn message_name<M: DefoldMessage>() -> MessageName {
+    const { assert!(M::NAME.len() < 32, "message name longer than 31 bytes") };
+    MessageName::new(ztr32::create(M::NAME))
+}
+
+/// Posts `message` to `receiver_url` under `M::NAME`, by hash when the URL carries
+/// its hashed form.
+pub fn send<M: DefoldMessage>(receiver_url: URL, message: M) -> Result<(), PayloadError> {
+    match receiver_url.hashed() {
+        Some(hashed_url) => {
+            defold::post_message_payload_hashed(hashed_url, message_hash::<M>(), message)
+        }
+        None => defold::post_message_payload(receiver_url, message_name::<M>(), message),
+    }
+}
+
+/// Decodes `incoming` as `M`, `None` if it is a different message.
+/// An empty payload is read as `null`, so unit structs can be sent without data.
+pub fn try_decode<M: DefoldMessage>(
+    incoming: &DefoldMessageReceived,
+) -> Option<Result<M, serde_json_core::de::Error>> {
+    if incoming.id != message_hash::<M>() {
+        return None;
+    }
+    let payload = if incoming.payload.is_empty() {
+        b"null"
+    } else {
+        incoming.payload.as_slice()
+    };
+    Some(serde_json_core::from_slice(payload).map(|(message, _)| message))
+}
+
+/// Message delivered to the app from a Defold `on_message` callback.
+#[derive(Event, Clone)]
+pub struct DefoldMessageReceived {
+    pub sender: URL,
+    pub id: dmhash_t,
+    /// Raw JSON payload, empty if the message carried no data.
+    pub payload: Vec<u8>,
+}
+
+impl DefoldMessageReceived {
+    pub fn payload_as<'a, T: Deserialize<'a>>(&'a self) -> Result<T, serde_json_core::de::Error> {
+        serde_json_core::from_slice(&self.payload).map(|(value, _)| value)
+    }
+}
+
+/// Messages received between two frames, emitted as events at the start of the next one.
+#[derive(Resource, Default)]
+pub(crate) struct PendingDefoldMessages(Vec<DefoldMessageReceived>);
+
+pub(crate) fn queue_defold_message(world: &mut World, message: DefoldMessageReceived) {
+    world
+        .get_resource_or_insert_with(PendingDefoldMessages::default)
+        .0
+        .push(message);
+}
+
+fn flush_pending_messages(
+    mut pending: ResMut<PendingDefoldMessages>,
+    mut events: EventWriter<DefoldMessageReceived>,
+) {
+    events.send_batch(pending.0.drain(..));
+}
+
+/// Systems reading [`DefoldMessageReceived`] should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DefoldInboundSet;
+
+pub struct DefoldMessagesPlugin;
+impl Plugin for DefoldMessagesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DefoldMessageReceived>()
+            .init_resource::<PendingDefoldMessages>()
+            .add_systems(PreUpdate, flush_pending_messages.in_set(DefoldInboundSet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::Update;
+    use no_std_strings::ztr64;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::dmhash;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Damage {
+        amount: u32,
+    }
+
+    #[derive(Resource, Default)]
+    struct ReceivedDamage(Vec<(dmhash_t, Damage)>);
+
+    fn collect_damage(
+        mut events: EventReader<DefoldMessageReceived>,
+        mut received: ResMut<ReceivedDamage>,
+    ) {
+        for event in events.read() {
+            received.0.push((event.id, event.payload_as().unwrap()));
+        }
+    }
+
+    fn message(id: dmhash_t, payload: &[u8]) -> DefoldMessageReceived {
+        DefoldMessageReceived {
+            sender: URL::new(ztr64::create("main:/unit#script")),
+            id,
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_queued_messages_delivered_on_next_update() {
+        let mut app = App::new();
+        app.add_plugins(DefoldMessagesPlugin)
+            .init_resource::<ReceivedDamage>()
+            .add_systems(Update, collect_damage);
+
+        queue_defold_message(app.world_mut(), message(1, br#"{"amount":5}"#));
+        queue_defold_message(app.world_mut(), message(2, br#"{"amount":7}"#));
+        assert!(app.world().resource::<ReceivedDamage>().0.is_empty());
+
+        app.update();
+
+        assert_eq!(
+            app.world().resource::<ReceivedDamage>().0,
+            [(1, Damage { amount: 5 }), (2, Damage { amount: 7 })]
+        );
+
+        app.update();
+        assert_eq!(app.world().resource::<ReceivedDamage>().0.len(), 2);
+    }
+
+    #[test]
+    fn test_payload_as_reports_invalid_json() {
+        let message = message(1, b"not json");
+        assert!(message.payload_as::<Damage>().is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Heal {
+        amount: u32,
+    }
+
+    impl MessagePayload for Heal {}
+
+    impl DefoldMessage for Heal {
+        const NAME: &'static str = "heal";
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Stunned;
+
+    impl MessagePayload for Stunned {}
+
+    impl DefoldMessage for Stunned {
+        const NAME: &'static str = "stunned";
+    }
+
+    #[test]
+    fn test_try_decode_matches_by_hash() {
+        const HEAL: dmhash_t = dmhash!("heal");
+        const STUNNED: dmhash_t = dmhash!("stunned");
+
+        assert_eq!(message_hash::<Heal>(), HEAL);
+        assert_eq!(
+            try_decode::<Heal>(&message(HEAL, br#"{"amount":4}"#)),
+            Some(Ok(Heal { amount: 4 }))
+        );
+        assert_eq!(try_decode::<Heal>(&message(STUNNED, b"")), None);
+        assert_eq!(
+            try_decode::<Stunned>(&message(STUNNED, b"")),
+            Some(Ok(Stunned))
+        );
+        assert!(matches!(
+            try_decode::<Heal>(&message(HEAL, br#"{"amount":"a lot"}"#)),
+            Some(Err(_))
+        ));
+    }
+}
@@ -4,6 +4,8 @@ use bevy_math::prelude::*;
 use bevy_time::prelude::*;
 use bevy_transform::components::Transform;
 
+use crate::simulation::SimulationSet;
+
 const GRAVITY_VALUE: f32 = -9.81;
 
 #[derive(Component)]
@@ -41,6 +43,129 @@ pub struct GravityScale {
     value: f32,
 }
 
+#[cfg(feature = "snapshot")]
+mod snapshot {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::snapshot::{SnapshotAppExt, SnapshotComponent};
+
+    impl SnapshotComponent for Speed {
+        const KEY: &'static str = "particle_speed";
+        type Saved = [f32; 3];
+
+        fn save(&self) -> Self::Saved {
+            self.value.to_array()
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self {
+                value: Vec3::from_array(saved),
+            })
+        }
+    }
+
+    impl SnapshotComponent for Friction {
+        const KEY: &'static str = "particle_friction";
+        type Saved = f32;
+
+        fn save(&self) -> Self::Saved {
+            self.value
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self { value: saved })
+        }
+    }
+
+    impl SnapshotComponent for ScaleSpeed {
+        const KEY: &'static str = "particle_scale_speed";
+        type Saved = [f32; 3];
+
+        fn save(&self) -> Self::Saved {
+            self.value.to_array()
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self {
+                value: Vec3::from_array(saved),
+            })
+        }
+    }
+
+    impl SnapshotComponent for RotationSpeed {
+        const KEY: &'static str = "particle_rotation_speed";
+        type Saved = [f32; 4];
+
+        fn save(&self) -> Self::Saved {
+            self.value.to_array()
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self {
+                value: Quat::from_array(saved),
+            })
+        }
+    }
+
+    impl SnapshotComponent for RotationFriction {
+        const KEY: &'static str = "particle_rotation_friction";
+        type Saved = f32;
+
+        fn save(&self) -> Self::Saved {
+            self.value
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self { value: saved })
+        }
+    }
+
+    impl SnapshotComponent for LifetimeRemaining {
+        const KEY: &'static str = "particle_lifetime";
+        /// Total and elapsed seconds.
+        type Saved = (f32, f32);
+
+        fn save(&self) -> Self::Saved {
+            (
+                self.life_remaining.duration().as_secs_f32(),
+                self.life_remaining.elapsed_secs(),
+            )
+        }
+
+        fn restore((duration, elapsed): Self::Saved) -> Option<Self> {
+            let duration = Duration::try_from_secs_f32(duration).ok()?;
+            let elapsed = Duration::try_from_secs_f32(elapsed).ok()?;
+            let mut life_remaining = Timer::new(duration, TimerMode::Once);
+            life_remaining.set_elapsed(elapsed);
+            Some(Self { life_remaining })
+        }
+    }
+
+    impl SnapshotComponent for GravityScale {
+        const KEY: &'static str = "particle_gravity_scale";
+        type Saved = f32;
+
+        fn save(&self) -> Self::Saved {
+            self.value
+        }
+
+        fn restore(saved: Self::Saved) -> Option<Self> {
+            Some(Self { value: saved })
+        }
+    }
+
+    pub(super) fn register_snapshot_components(app: &mut App) {
+        app.register_snapshot_component::<Speed>()
+            .register_snapshot_component::<Friction>()
+            .register_snapshot_component::<ScaleSpeed>()
+            .register_snapshot_component::<RotationSpeed>()
+            .register_snapshot_component::<RotationFriction>()
+            .register_snapshot_component::<LifetimeRemaining>()
+            .register_snapshot_component::<GravityScale>();
+    }
+}
+
 fn rotation_friction_system(
     mut query: Query<(&mut RotationSpeed, &RotationFriction)>,
     time: Res<Time>,
@@ -109,19 +234,23 @@ struct ParticleSet;
 pub struct ParticlesPlugin;
 impl Plugin for ParticlesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                move_system,
-                scale_system,
-                rotate_entities_system,
-                gravity_system,
-                friction_system,
-                rotation_friction_system,
-                lifetime_system,
-            )
-                .in_set(ParticleSet),
-        );
+        #[cfg(feature = "snapshot")]
+        snapshot::register_snapshot_components(app);
+
+        app.configure_sets(Update, ParticleSet.in_set(SimulationSet))
+            .add_systems(
+                Update,
+                (
+                    move_system,
+                    scale_system,
+                    rotate_entities_system,
+                    gravity_system,
+                    friction_system,
+                    rotation_friction_system,
+                    lifetime_system,
+                )
+                    .in_set(ParticleSet),
+            );
     }
 }
 
@@ -158,4 +287,16 @@ mod tests {
         }
         assert!(app.world().get_entity(entity).is_err());
     }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_restore_lifetime_rejects_out_of_range_seconds() {
+        use crate::snapshot::SnapshotComponent;
+
+        assert!(LifetimeRemaining::restore((1.0, 0.5)).is_some());
+        assert!(LifetimeRemaining::restore((1e30, 0.0)).is_none());
+        assert!(LifetimeRemaining::restore((1.0, 1e30)).is_none());
+        assert!(LifetimeRemaining::restore((f32::NAN, 0.0)).is_none());
+        assert!(LifetimeRemaining::restore((-1.0, 0.0)).is_none());
+    }
 }
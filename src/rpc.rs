@@ -0,0 +1,211 @@
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{
+    prelude::*,
+    system::{In, IntoSystem},
+    world::Mut,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    defold::{PayloadError, to_json_vec},
+    defold_cpp_interface::dmhash_t,
+};
+
+/// Largest JSON reply a handler may produce.
+pub const MAX_RPC_REPLY_LEN: usize = 64 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum RpcError {
+    UnknownMethod,
+    InvalidArguments(serde_json_core::de::Error),
+    /// The handler system could not run: it is already running (a recursive
+    /// call from inside itself) or was removed. A reentrant `app_call` from Lua
+    /// never gets here, it returns `AppStatus::AppBusy`.
+    HandlerFailed,
+    /// The reply is longer than [`MAX_RPC_REPLY_LEN`].
+    ReplyTooLarge,
+    /// The reply can't be written as JSON.
+    InvalidReply,
+}
+
+type RpcHandler = Box<dyn Fn(&mut World, &[u8]) -> Result<Vec<u8>, RpcError> + Send + Sync>;
+
+/// Methods Lua can call synchronously through `app_call`, keyed by the hash of their name.
+#[derive(Resource, Default)]
+pub struct RpcRegistry {
+    handlers: BTreeMap<dmhash_t, RpcHandler>,
+}
+
+impl RpcRegistry {
+    pub fn contains(&self, method: dmhash_t) -> bool {
+        self.handlers.contains_key(&method)
+    }
+}
+
+pub trait RpcAppExt {
+    /// Registers `handler` as a one-shot system: its `In` argument is parsed from the
+    /// JSON arguments of the call and its output is sent back as JSON. Registering
+    /// the same method again replaces the previous handler.
+    ///
+    /// A missing resource panics inside a one-shot system, take resources that may be
+    /// absent as `Option<Res<_>>`.
+    fn add_rpc_method<A, R, M>(
+        &mut self,
+        method: dmhash_t,
+        handler: impl IntoSystem<In<A>, R, M> + 'static,
+    ) -> &mut Self
+    where
+        A: DeserializeOwned + 'static,
+        R: Serialize + 'static;
+}
+
+impl RpcAppExt for App {
+    fn add_rpc_method<A, R, M>(
+        &mut self,
+        method: dmhash_t,
+        handler: impl IntoSystem<In<A>, R, M> + 'static,
+    ) -> &mut Self
+    where
+        A: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+    {
+        let world = self.world_mut();
+        let system_id = world.register_system(handler);
+        let handler: RpcHandler = Box::new(move |world: &mut World, args_json: &[u8]| {
+            let (args, _) =
+                serde_json_core::from_slice::<A>(args_json).map_err(RpcError::InvalidArguments)?;
+            let reply = world
+                .run_system_with_input(system_id, args)
+                .map_err(|_| RpcError::HandlerFailed)?;
+            to_json_vec(&reply, MAX_RPC_REPLY_LEN).map_err(|error| match error {
+                PayloadError::TooLarge => RpcError::ReplyTooLarge,
+                PayloadError::Serialize => RpcError::InvalidReply,
+            })
+        });
+        world
+            .get_resource_or_insert_with(RpcRegistry::default)
+            .handlers
+            .insert(method, handler);
+        self
+    }
+}
+
+/// Runs the handler registered for `method` and returns its JSON reply.
+/// Empty arguments are read as `null`, so handlers taking `()` can be called without any.
+pub(crate) fn call_rpc(
+    world: &mut World,
+    method: dmhash_t,
+    args_json: &[u8],
+) -> Result<Vec<u8>, RpcError> {
+    let args_json = if args_json.is_empty() {
+        b"null"
+    } else {
+        args_json
+    };
+    world
+        .get_resource::<RpcRegistry>()
+        .filter(|registry| registry.contains(method))
+        .ok_or(RpcError::UnknownMethod)?;
+    world.resource_scope(|world, registry: Mut<RpcRegistry>| {
+        (registry.handlers[&method])(world, args_json)
+    })
+}
+
+pub struct RpcPlugin;
+impl Plugin for RpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RpcRegistry>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    const ADD: dmhash_t = 1;
+    const SCORE: dmhash_t = 2;
+    const DUMP: dmhash_t = 3;
+
+    #[derive(Deserialize)]
+    struct AddArgs {
+        a: i32,
+        b: i32,
+    }
+
+    #[derive(Resource)]
+    struct Score(u32);
+
+    fn add(In(args): In<AddArgs>) -> i32 {
+        args.a + args.b
+    }
+
+    fn score(In(()): In<()>, score: Option<Res<Score>>) -> Option<u32> {
+        score.map(|score| score.0)
+    }
+
+    fn rpc_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(RpcPlugin)
+            .add_rpc_method(ADD, add)
+            .add_rpc_method(SCORE, score)
+            .add_rpc_method(DUMP, |In(len): In<usize>| alloc::vec![0u8; len]);
+        app
+    }
+
+    #[test]
+    fn test_call_returns_json_reply() {
+        let mut app = rpc_app();
+
+        assert_eq!(
+            call_rpc(app.world_mut(), ADD, br#"{"a": 2, "b": 40}"#),
+            Ok(b"42".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_handler_reads_world() {
+        let mut app = rpc_app();
+        app.insert_resource(Score(7));
+
+        assert_eq!(call_rpc(app.world_mut(), SCORE, b""), Ok(b"7".to_vec()));
+    }
+
+    #[test]
+    fn test_optional_resource_missing_replies_null() {
+        let mut app = rpc_app();
+
+        assert_eq!(call_rpc(app.world_mut(), SCORE, b""), Ok(b"null".to_vec()));
+    }
+
+    #[test]
+    fn test_unknown_method_and_bad_arguments_rejected() {
+        let mut app = rpc_app();
+
+        assert_eq!(
+            call_rpc(app.world_mut(), 99, b"{}"),
+            Err(RpcError::UnknownMethod)
+        );
+        assert!(matches!(
+            call_rpc(app.world_mut(), ADD, br#"{"a": 2}"#),
+            Err(RpcError::InvalidArguments(_))
+        ));
+    }
+
+    #[test]
+    fn test_oversized_reply_rejected() {
+        let mut app = rpc_app();
+
+        assert_eq!(
+            call_rpc(app.world_mut(), DUMP, b"3"),
+            Ok(b"[0,0,0]".to_vec())
+        );
+        assert_eq!(
+            call_rpc(app.world_mut(), DUMP, b"40000"),
+            Err(RpcError::ReplyTooLarge)
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use core::time::Duration;
+
+use bevy_app::{App, FixedUpdate, Plugin, Update};
+use bevy_ecs::{prelude::*, world::World};
+use bevy_time::{Time, TimeUpdateStrategy, Virtual};
+
+use crate::{
+    app_registry::AppStatus,
+    frame_phases::{
+        FrameDriver, claim_whole_frame_driver, run_fixed_step_phase, run_late_update_phase,
+        run_update_phase,
+    },
+    host_time::{TimeMode, set_host_delta},
+};
+
+/// Debug controls over the gameplay clock. While paused, `Virtual` time stands still
+/// and [`SimulationSet`] is skipped, everything else (inbound messages, input, views)
+/// keeps running.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct SimulationControl {
+    paused: bool,
+    /// Set while `app_step` runs its frames.
+    stepping: bool,
+    time_scale: f32,
+}
+
+impl Default for SimulationControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            stepping: false,
+            time_scale: 1.0,
+        }
+    }
+}
+
+impl SimulationControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+}
+
+/// Gameplay systems that stop while the simulation is paused, in `Update` and `FixedUpdate`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationSet;
+
+fn simulation_running(control: Option<Res<SimulationControl>>) -> bool {
+    control.is_none_or(|control| !control.paused || control.stepping)
+}
+
+pub(crate) fn pause_simulation(world: &mut World) {
+    world.resource_mut::<SimulationControl>().paused = true;
+    world.resource_mut::<Time<Virtual>>().pause();
+}
+
+pub(crate) fn resume_simulation(world: &mut World) {
+    world.resource_mut::<SimulationControl>().paused = false;
+    world.resource_mut::<Time<Virtual>>().unpause();
+}
+
+pub(crate) fn set_time_scale(world: &mut World, time_scale: f32) -> Result<(), AppStatus> {
+    if !time_scale.is_finite() || time_scale < 0.0 {
+        return Err(AppStatus::InvalidArgument);
+    }
+    world.resource_mut::<SimulationControl>().time_scale = time_scale;
+    world
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed(time_scale);
+    Ok(())
+}
+
+/// Runs one frame advancing by `dt`, through the same path the app is normally driven by.
+/// Phase driven apps also run one fixed step, with the timestep the host set.
+fn run_step_frame(app: &mut App, dt_seconds: f32) -> Result<(), AppStatus> {
+    let world = app.world_mut();
+    if *world.resource::<TimeMode>() == TimeMode::WallClock {
+        let dt = Duration::try_from_secs_f32(dt_seconds).map_err(|_| AppStatus::InvalidArgument)?;
+        let strategy = world
+            .remove_resource::<TimeUpdateStrategy>()
+            .unwrap_or_default();
+        world.insert_resource(TimeUpdateStrategy::ManualDuration(dt));
+        // A step longer than `max_delta` must not be clamped either.
+        let max_delta = world.resource::<Time<Virtual>>().max_delta();
+        world
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(max_delta.max(dt));
+        app.update();
+        let world = app.world_mut();
+        world.insert_resource(strategy);
+        world
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(max_delta);
+        return Ok(());
+    }
+
+    if let Some(FrameDriver::Phases { .. }) = world.get_resource::<FrameDriver>() {
+        run_fixed_step_phase(world)?;
+        run_update_phase(world, dt_seconds)?;
+        return run_late_update_phase(world);
+    }
+
+    claim_whole_frame_driver(world)?;
+    set_host_delta(world, dt_seconds)?;
+    app.update();
+    Ok(())
+}
+
+/// Advances a paused app by `frames` frames of `dt_seconds` each, it stays paused afterwards.
+pub(crate) fn step_simulation(
+    app: &mut App,
+    frames: u32,
+    dt_seconds: f32,
+) -> Result<(), AppStatus> {
+    // Checked up front so a bad step leaves the app paused without running a frame.
+    let valid_dt = Duration::try_from_secs_f32(dt_seconds).is_ok_and(|dt| !dt.is_zero());
+    if !valid_dt {
+        return Err(AppStatus::InvalidArgument);
+    }
+    if !app.world().resource::<SimulationControl>().paused {
+        return Err(AppStatus::NotPaused);
+    }
+
+    let world = app.world_mut();
+    world.resource_mut::<SimulationControl>().stepping = true;
+    world.resource_mut::<Time<Virtual>>().unpause();
+
+    let mut result = Ok(());
+    for _ in 0..frames {
+        result = run_step_frame(app, dt_seconds);
+        if result.is_err() {
+            break;
+        }
+    }
+
+    let world = app.world_mut();
+    world.resource_mut::<SimulationControl>().stepping = false;
+    world.resource_mut::<Time<Virtual>>().pause();
+    result
+}
+
+pub struct SimulationControlPlugin;
+impl Plugin for SimulationControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationControl>()
+            .configure_sets(Update, SimulationSet.run_if(simulation_running))
+            .configure_sets(FixedUpdate, SimulationSet.run_if(simulation_running));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_time::TimePlugin;
+
+    use super::*;
+    use crate::frame_phases::run_fixed_update_phase;
+    use crate::host_time::configure_time;
+
+    #[derive(Resource, Default)]
+    struct Counters {
+        gameplay_frames: u32,
+        other_frames: u32,
+        gameplay_time: f32,
+    }
+
+    fn app(time_mode: TimeMode) -> App {
+        let mut app = App::new();
+        app.add_plugins((TimePlugin, SimulationControlPlugin))
+            .init_resource::<Counters>()
+            .add_systems(
+                Update,
+                (
+                    (|time: Res<Time>, mut counters: ResMut<Counters>| {
+                        counters.gameplay_frames += 1;
+                        counters.gameplay_time += time.delta_secs();
+                    })
+                    .in_set(SimulationSet),
+                    |mut counters: ResMut<Counters>| counters.other_frames += 1,
+                ),
+            );
+        configure_time(&mut app, time_mode);
+        app
+    }
+
+    fn host_frame(app: &mut App, dt_seconds: f32) {
+        set_host_delta(app.world_mut(), dt_seconds).unwrap();
+        app.update();
+    }
+
+    #[test]
+    fn test_paused_frames_skip_simulation_set() {
+        let mut app = app(TimeMode::HostDriven);
+        host_frame(&mut app, 0.5);
+        pause_simulation(app.world_mut());
+        host_frame(&mut app, 0.5);
+        host_frame(&mut app, 0.5);
+
+        let counters = app.world().resource::<Counters>();
+        assert_eq!(counters.gameplay_frames, 1);
+        assert_eq!(counters.other_frames, 3);
+        assert_eq!(app.world().resource::<Time<Virtual>>().elapsed_secs(), 0.5);
+    }
+
+    #[test]
+    fn test_step_runs_frames_and_stays_paused() {
+        let mut app = app(TimeMode::HostDriven);
+        pause_simulation(app.world_mut());
+
+        step_simulation(&mut app, 3, 0.25).unwrap();
+        host_frame(&mut app, 0.25);
+
+        let counters = app.world().resource::<Counters>();
+        assert_eq!(counters.gameplay_frames, 3);
+        assert_eq!(counters.gameplay_time, 0.75);
+        assert!(app.world().resource::<SimulationControl>().is_paused());
+    }
+
+    #[test]
+    fn test_step_in_wall_clock_mode_uses_given_dt() {
+        let mut app = app(TimeMode::WallClock);
+        app.update();
+        pause_simulation(app.world_mut());
+
+        step_simulation(&mut app, 2, 0.125).unwrap();
+
+        let counters = app.world().resource::<Counters>();
+        assert_eq!(counters.gameplay_frames, 3);
+        assert_eq!(app.world().resource::<Time>().delta_secs(), 0.125);
+        assert!(app.world().get_resource::<TimeUpdateStrategy>().is_some());
+    }
+
+    #[test]
+    fn test_wall_clock_step_longer_than_max_delta_not_clamped() {
+        let mut app = app(TimeMode::WallClock);
+        app.update();
+        pause_simulation(app.world_mut());
+
+        step_simulation(&mut app, 1, 0.5).unwrap();
+
+        let time = app.world().resource::<Time<Virtual>>();
+        assert_eq!(time.delta_secs(), 0.5);
+        assert_eq!(time.max_delta(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_phase_step_keeps_host_fixed_timestep() {
+        let mut app = app(TimeMode::HostDriven);
+        let world = app.world_mut();
+        run_fixed_update_phase(world, 0.5).unwrap();
+        pause_simulation(world);
+
+        step_simulation(&mut app, 2, 0.125).unwrap();
+
+        let fixed_time = app.world().resource::<Time<bevy_time::Fixed>>();
+        assert_eq!(fixed_time.timestep().as_secs_f32(), 0.5);
+        assert_eq!(fixed_time.elapsed_secs(), 1.5);
+    }
+
+    #[test]
+    fn test_step_requires_pause() {
+        let mut app = app(TimeMode::HostDriven);
+
+        assert_eq!(step_simulation(&mut app, 1, 0.1), Err(AppStatus::NotPaused));
+    }
+
+    #[test]
+    fn test_step_rejects_out_of_range_dt() {
+        let mut app = app(TimeMode::WallClock);
+        pause_simulation(app.world_mut());
+
+        assert_eq!(
+            step_simulation(&mut app, 1, 1e30),
+            Err(AppStatus::InvalidArgument)
+        );
+        assert_eq!(
+            step_simulation(&mut app, 1, 0.0),
+            Err(AppStatus::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_time_scale_scales_delta() {
+        let mut app = app(TimeMode::HostDriven);
+        set_time_scale(app.world_mut(), 0.5).unwrap();
+        host_frame(&mut app, 0.5);
+
+        assert_eq!(app.world().resource::<Counters>().gameplay_time, 0.25);
+        assert_eq!(
+            set_time_scale(app.world_mut(), -1.0),
+            Err(AppStatus::InvalidArgument)
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_math::{Quat, Vec3};
+use bevy_transform::components::Transform;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{rng::GameRng, view::DefoldView};
+
+/// Bumped whenever the layout of the blob changes, older blobs are rejected.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Component saved into world snapshots.
+///
+/// Entities are renumbered on load, so a component holding an `Entity` can not be
+/// restored as is.
+pub trait SnapshotComponent: Component + Sized {
+    /// Name of the section in the blob, must stay stable between releases.
+    const KEY: &'static str;
+    type Saved: Serialize + DeserializeOwned;
+
+    fn save(&self) -> Self::Saved;
+    /// Returns `None` when the saved data does not describe a valid component.
+    fn restore(saved: Self::Saved) -> Option<Self>;
+}
+
+/// Resource saved into world snapshots, see [`SnapshotComponent`].
+pub trait SnapshotResource: Resource + Sized {
+    const KEY: &'static str;
+    type Saved: Serialize + DeserializeOwned;
+
+    fn save(&self) -> Self::Saved;
+    fn restore(saved: Self::Saved) -> Option<Self>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    Encode(postcard::Error),
+    Decode(postcard::Error),
+    UnsupportedVersion(u32),
+    /// The blob has a section no registered type claims.
+    UnknownKey(String),
+    /// A section decoded fine but its content is out of range.
+    InvalidData,
+}
+
+type EntityIndices = BTreeMap<Entity, u32>;
+/// Entity index in the blob and the encoded component.
+type ComponentEntry = (u32, Vec<u8>);
+type ComponentEntries = Vec<ComponentEntry>;
+type SavedResource = Result<Vec<u8>, postcard::Error>;
+type ApplyComponents = Box<dyn FnOnce(&mut World, &[Entity])>;
+type ApplyResource = Box<dyn FnOnce(&mut World)>;
+
+#[derive(Clone, Copy)]
+struct ComponentRegistration {
+    key: &'static str,
+    entities: fn(&mut World) -> Vec<Entity>,
+    save: fn(&mut World, &mut EntityIndices) -> Result<ComponentEntries, postcard::Error>,
+    load: fn(&[ComponentEntry]) -> Result<ApplyComponents, SnapshotError>,
+}
+
+#[derive(Clone, Copy)]
+struct ResourceRegistration {
+    key: &'static str,
+    save: fn(&World) -> Option<SavedResource>,
+    load: fn(&[u8]) -> Result<ApplyResource, SnapshotError>,
+}
+
+/// Types that take part in [`save_world`] and [`load_world`].
+#[derive(Resource, Default, Clone)]
+pub struct SnapshotRegistry {
+    components: Vec<ComponentRegistration>,
+    resources: Vec<ResourceRegistration>,
+}
+
+fn component_entities<T: SnapshotComponent>(world: &mut World) -> Vec<Entity> {
+    world
+        .query_filtered::<Entity, With<T>>()
+        .iter(world)
+        .collect()
+}
+
+fn save_components<T: SnapshotComponent>(
+    world: &mut World,
+    indices: &mut EntityIndices,
+) -> Result<ComponentEntries, postcard::Error> {
+    let mut entries = Vec::new();
+    for (entity, component) in world.query::<(Entity, &T)>().iter(world) {
+        let next_index = indices.len() as u32;
+        let index = *indices.entry(entity).or_insert(next_index);
+        entries.push((index, postcard::to_allocvec(&component.save())?));
+    }
+    Ok(entries)
+}
+
+fn load_components<T: SnapshotComponent>(
+    entries: &[ComponentEntry],
+) -> Result<ApplyComponents, SnapshotError> {
+    let components = entries
+        .iter()
+        .map(|(index, data)| {
+            let saved = postcard::from_bytes(data).map_err(SnapshotError::Decode)?;
+            let component = T::restore(saved).ok_or(SnapshotError::InvalidData)?;
+            Ok((*index, component))
+        })
+        .collect::<Result<Vec<_>, SnapshotError>>()?;
+
+    Ok(Box::new(move |world: &mut World, entities: &[Entity]| {
+        for (index, component) in components {
+            world.entity_mut(entities[index as usize]).insert(component);
+        }
+    }))
+}
+
+fn save_resource<T: SnapshotResource>(world: &World) -> Option<SavedResource> {
+    world
+        .get_resource::<T>()
+        .map(|resource| postcard::to_allocvec(&resource.save()))
+}
+
+fn load_resource<T: SnapshotResource>(data: &[u8]) -> Result<ApplyResource, SnapshotError> {
+    let saved = postcard::from_bytes(data).map_err(SnapshotError::Decode)?;
+    let resource = T::restore(saved).ok_or(SnapshotError::InvalidData)?;
+    Ok(Box::new(move |world: &mut World| {
+        world.insert_resource(resource);
+    }))
+}
+
+pub trait SnapshotAppExt {
+    fn register_snapshot_component<T: SnapshotComponent>(&mut self) -> &mut Self;
+    fn register_snapshot_resource<T: SnapshotResource>(&mut self) -> &mut Self;
+}
+
+impl SnapshotAppExt for App {
+    fn register_snapshot_component<T: SnapshotComponent>(&mut self) -> &mut Self {
+        let mut registry = self
+            .world_mut()
+            .get_resource_or_insert_with(SnapshotRegistry::default);
+        if !registry.components.iter().any(|entry| entry.key == T::KEY) {
+            registry.components.push(ComponentRegistration {
+                key: T::KEY,
+                entities: component_entities::<T>,
+                save: save_components::<T>,
+                load: load_components::<T>,
+            });
+        }
+        self
+    }
+
+    fn register_snapshot_resource<T: SnapshotResource>(&mut self) -> &mut Self {
+        let mut registry = self
+            .world_mut()
+            .get_resource_or_insert_with(SnapshotRegistry::default);
+        if !registry.resources.iter().any(|entry| entry.key == T::KEY) {
+            registry.resources.push(ResourceRegistration {
+                key: T::KEY,
+                save: save_resource::<T>,
+                load: load_resource::<T>,
+            });
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Section<T> {
+    key: String,
+    data: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotBody {
+    entity_count: u32,
+    components: Vec<Section<ComponentEntries>>,
+    resources: Vec<Section<Vec<u8>>>,
+}
+
+/// Serializes every registered component and resource into a postcard blob
+/// prefixed with [`SNAPSHOT_VERSION`].
+pub fn save_world(world: &mut World) -> Result<Vec<u8>, SnapshotError> {
+    let registry = world
+        .get_resource::<SnapshotRegistry>()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut indices = EntityIndices::new();
+    let mut components = Vec::new();
+    for entry in &registry.components {
+        let data = (entry.save)(world, &mut indices).map_err(SnapshotError::Encode)?;
+        if !data.is_empty() {
+            components.push(Section {
+                key: entry.key.to_string(),
+                data,
+            });
+        }
+    }
+
+    let mut resources = Vec::new();
+    for entry in &registry.resources {
+        if let Some(data) = (entry.save)(world) {
+            resources.push(Section {
+                key: entry.key.to_string(),
+                data: data.map_err(SnapshotError::Encode)?,
+            });
+        }
+    }
+
+    let body = SnapshotBody {
+        entity_count: indices.len() as u32,
+        components,
+        resources,
+    };
+    postcard::to_allocvec(&(SNAPSHOT_VERSION, body)).map_err(SnapshotError::Encode)
+}
+
+/// Replaces every entity holding a registered component, and the registered
+/// resources present in `blob`, with the saved ones. The world is left untouched
+/// when the blob is rejected.
+pub fn load_world(world: &mut World, blob: &[u8]) -> Result<(), SnapshotError> {
+    let (version, body) = postcard::take_from_bytes::<u32>(blob).map_err(SnapshotError::Decode)?;
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let body: SnapshotBody = postcard::from_bytes(body).map_err(SnapshotError::Decode)?;
+
+    let registry = world
+        .get_resource::<SnapshotRegistry>()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut apply_components = Vec::new();
+    let mut referenced = BTreeSet::new();
+    for section in &body.components {
+        let entry = registry
+            .components
+            .iter()
+            .find(|entry| entry.key == section.key)
+            .ok_or_else(|| SnapshotError::UnknownKey(section.key.clone()))?;
+        if section
+            .data
+            .iter()
+            .any(|(index, _)| *index >= body.entity_count)
+        {
+            return Err(SnapshotError::InvalidData);
+        }
+        referenced.extend(section.data.iter().map(|(index, _)| *index));
+        apply_components.push((entry.load)(&section.data)?);
+    }
+    // Every saved entity holds at least one component, a larger count only comes
+    // from a corrupted blob and would spawn that many empty entities.
+    if body.entity_count as usize > referenced.len() {
+        return Err(SnapshotError::InvalidData);
+    }
+
+    let mut apply_resources = Vec::new();
+    for section in &body.resources {
+        let entry = registry
+            .resources
+            .iter()
+            .find(|entry| entry.key == section.key)
+            .ok_or_else(|| SnapshotError::UnknownKey(section.key.clone()))?;
+        apply_resources.push((entry.load)(&section.data)?);
+    }
+
+    let mut saved_entities = BTreeSet::new();
+    for entry in &registry.components {
+        saved_entities.extend((entry.entities)(world));
+    }
+    for entity in saved_entities {
+        world.despawn(entity);
+    }
+
+    let entities: Vec<Entity> = (0..body.entity_count)
+        .map(|_| world.spawn_empty().id())
+        .collect();
+    for apply in apply_components {
+        apply(world, &entities);
+    }
+    for apply in apply_resources {
+        apply(world);
+    }
+    Ok(())
+}
+
+impl SnapshotComponent for Transform {
+    const KEY: &'static str = "transform";
+    type Saved = ([f32; 3], [f32; 4], [f32; 3]);
+
+    fn save(&self) -> Self::Saved {
+        (
+            self.translation.to_array(),
+            self.rotation.to_array(),
+            self.scale.to_array(),
+        )
+    }
+
+    fn restore((translation, rotation, scale): Self::Saved) -> Option<Self> {
+        Some(Transform {
+            translation: Vec3::from_array(translation),
+            rotation: Quat::from_array(rotation),
+            scale: Vec3::from_array(scale),
+        })
+    }
+}
+
+/// Registers the snapshot types of the bridge itself, other plugins register theirs
+/// through [`SnapshotAppExt`].
+pub struct SnapshotPlugin;
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapshotRegistry>()
+            .register_snapshot_component::<Transform>()
+            .register_snapshot_component::<DefoldView>()
+            .register_snapshot_resource::<GameRng>();
+        #[cfg(feature = "board")]
+        app.register_snapshot_resource::<crate::board::Board>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Health(u32);
+
+    impl SnapshotComponent for Health {
+        const KEY: &'static str = "health";
+        type Saved = u32;
+
+        fn save(&self) -> u32 {
+            self.0
+        }
+
+        fn restore(saved: u32) -> Option<Self> {
+            (saved > 0).then_some(Health(saved))
+        }
+    }
+
+    #[derive(Component)]
+    struct Unsaved;
+
+    fn snapshot_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(SnapshotPlugin)
+            .register_snapshot_component::<Health>();
+        app
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let mut app = snapshot_app();
+        let world = app.world_mut();
+        world.spawn((Transform::from_xyz(1.0, 2.0, 3.0), Health(5)));
+        world.spawn(Health(7));
+        world.insert_resource(GameRng::new(9));
+        let blob = save_world(world).unwrap();
+
+        world.spawn(Health(1));
+        world.resource_mut::<GameRng>().next_u64();
+        load_world(world, &blob).unwrap();
+
+        let mut saved: Vec<_> = world
+            .query::<(&Health, Option<&Transform>)>()
+            .iter(world)
+            .map(|(health, transform)| (health.0, transform.map(|t| t.translation)))
+            .collect();
+        saved.sort_by_key(|(health, _)| *health);
+        assert_eq!(saved, [(5, Some(Vec3::new(1.0, 2.0, 3.0))), (7, None)]);
+        assert_eq!(*world.resource::<GameRng>(), GameRng::new(9));
+    }
+
+    #[test]
+    fn test_load_keeps_entities_without_snapshot_components() {
+        let mut app = snapshot_app();
+        let world = app.world_mut();
+        let blob = save_world(world).unwrap();
+        let kept = world.spawn(Unsaved).id();
+
+        load_world(world, &blob).unwrap();
+
+        assert!(world.get_entity(kept).is_ok());
+    }
+
+    #[test]
+    fn test_other_version_rejected() {
+        let mut app = snapshot_app();
+        let mut blob = save_world(app.world_mut()).unwrap();
+        blob[0] = SNAPSHOT_VERSION as u8 + 1;
+
+        assert_eq!(
+            load_world(app.world_mut(), &blob),
+            Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn test_rejected_blob_leaves_world_untouched() {
+        let mut app = snapshot_app();
+        app.world_mut().spawn(Health(3));
+        let blob = save_world(app.world_mut()).unwrap();
+
+        let mut other = App::new();
+        other.add_plugins(SnapshotPlugin);
+        let entity = other.world_mut().spawn(Transform::IDENTITY).id();
+
+        assert_eq!(
+            load_world(other.world_mut(), &blob),
+            Err(SnapshotError::UnknownKey("health".to_string()))
+        );
+        assert!(other.world().get_entity(entity).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_component_rejected() {
+        let mut app = snapshot_app();
+        let body = SnapshotBody {
+            entity_count: 1,
+            components: alloc::vec![Section {
+                key: "health".to_string(),
+                data: alloc::vec![(0, postcard::to_allocvec(&0u32).unwrap())],
+            }],
+            resources: Vec::new(),
+        };
+        let blob = postcard::to_allocvec(&(SNAPSHOT_VERSION, body)).unwrap();
+
+        assert_eq!(
+            load_world(app.world_mut(), &blob),
+            Err(SnapshotError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_entity_count_above_referenced_entities_rejected() {
+        let mut app = snapshot_app();
+        let entity = app.world_mut().spawn(Health(3)).id();
+        let body = SnapshotBody {
+            entity_count: u32::MAX,
+            components: Vec::new(),
+            resources: Vec::new(),
+        };
+        let blob = postcard::to_allocvec(&(SNAPSHOT_VERSION, body)).unwrap();
+
+        assert_eq!(
+            load_world(app.world_mut(), &blob),
+            Err(SnapshotError::InvalidData)
+        );
+        assert!(app.world().get_entity(entity).is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+use alloc::vec::Vec;
+
+use bevy_app::{App, Plugin, PostUpdate, PreUpdate};
+use bevy_ecs::prelude::*;
+use bevy_transform::components::Transform;
+
+use crate::{
+    defold::{self, URL},
+    defold_cpp_interface::{
+        GO_TRANSFORM_ALL, GO_TRANSFORM_ROTATION, GO_TRANSFORM_TRANSLATION, GoTransform,
+        GoTransformBatchEntry,
+    },
+    view::{DefoldViewSet, ViewUrl},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncChannels {
+    #[default]
+    All,
+    TranslationOnly,
+    RotationOnly,
+}
+
+/// Per entity sync options, entities without it sync every channel with the default thresholds.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct TransformSync {
+    pub channels: SyncChannels,
+    /// Minimal translation distance worth sending.
+    pub translation_threshold: f32,
+    /// Minimal rotation angle in radians worth sending.
+    pub rotation_threshold: f32,
+    /// Minimal scale change (per axis) worth sending.
+    pub scale_threshold: f32,
+}
+
+impl Default for TransformSync {
+    fn default() -> Self {
+        Self {
+            channels: SyncChannels::All,
+            translation_threshold: 0.001,
+            rotation_threshold: 0.001,
+            scale_threshold: 0.001,
+        }
+    }
+}
+
+/// Transform updates of the frame, sent to Defold in one call at the end of
+/// `PostUpdate`. Systems can queue their own updates until then.
+#[derive(Resource, Default)]
+pub struct TransformBatch {
+    urls: Vec<URL>,
+    updates: Vec<(GoTransform, u8)>,
+}
+
+impl TransformBatch {
+    /// Queues `transform` for the game object, writing only the `GO_TRANSFORM_*`
+    /// channels set in `channels`.
+    pub fn set(&mut self, url: URL, transform: Transform, channels: u8) {
+        self.urls.push(url);
+        self.updates.push((transform.into(), channels));
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Records for C++, string URLs point into the batch.
+    fn entries(&self) -> impl Iterator<Item = GoTransformBatchEntry> + '_ {
+        self.urls
+            .iter()
+            .zip(&self.updates)
+            .map(|(url, (transform, channels))| GoTransformBatchEntry {
+                url: url.as_cpp(),
+                transform: *transform,
+                channels: *channels,
+            })
+    }
+}
+
+fn flush_transform_batch(mut batch: ResMut<TransformBatch>) {
+    if batch.is_empty() {
+        return;
+    }
+    let entries: Vec<GoTransformBatchEntry> = batch.entries().collect();
+    defold::set_go_transforms_batch(&entries);
+    batch.urls.clear();
+    batch.updates.clear();
+}
+
+/// Marks views moved by Defold (physics, `go.animate`): their `Transform` is read
+/// back from the game object at the start of every frame and never pushed.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct PullTransform;
+
+/// Transform last pushed to the game object.
+#[derive(Component, Clone, Copy, Debug)]
+struct LastSyncedTransform(Transform);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SyncAction {
+    Skip,
+    All,
+    Translation,
+    Rotation,
+}
+
+fn sync_action(
+    options: &TransformSync,
+    last: Option<&Transform>,
+    current: &Transform,
+) -> SyncAction {
+    let (translation_changed, rotation_changed, scale_changed) = match last {
+        None => (true, true, true),
+        Some(last) => (
+            last.translation.distance(current.translation) > options.translation_threshold,
+            last.rotation.angle_between(current.rotation) > options.rotation_threshold,
+            (last.scale - current.scale).abs().max_element() > options.scale_threshold,
+        ),
+    };
+
+    match options.channels {
+        SyncChannels::All if translation_changed || rotation_changed || scale_changed => {
+            SyncAction::All
+        }
+        SyncChannels::TranslationOnly if translation_changed => SyncAction::Translation,
+        SyncChannels::RotationOnly if rotation_changed => SyncAction::Rotation,
+        _ => SyncAction::Skip,
+    }
+}
+
+type SyncedViewQuery<'a> = (
+    Entity,
+    &'a ViewUrl,
+    &'a Transform,
+    Option<&'a TransformSync>,
+    Option<&'a mut LastSyncedTransform>,
+);
+
+fn sync_transforms(
+    mut commands: Commands,
+    mut batch: ResMut<TransformBatch>,
+    mut query: Query<SyncedViewQuery, (Changed<Transform>, Without<PullTransform>)>,
+) {
+    for (entity, view_url, transform, options, last_synced) in &mut query {
+        let options = options.copied().unwrap_or_default();
+        let last = last_synced.as_deref().map(|last_synced| &last_synced.0);
+        let mut synced = last.copied().unwrap_or(*transform);
+
+        match sync_action(&options, last, transform) {
+            SyncAction::Skip => continue,
+            SyncAction::All => {
+                batch.set(view_url.0, *transform, GO_TRANSFORM_ALL);
+                synced = *transform;
+            }
+            SyncAction::Translation => {
+                batch.set(view_url.0, *transform, GO_TRANSFORM_TRANSLATION);
+                synced.translation = transform.translation;
+            }
+            SyncAction::Rotation => {
+                batch.set(view_url.0, *transform, GO_TRANSFORM_ROTATION);
+                synced.rotation = transform.rotation;
+            }
+        }
+
+        match last_synced {
+            Some(mut last_synced) => last_synced.0 = synced,
+            None => {
+                commands.entity(entity).insert(LastSyncedTransform(synced));
+            }
+        }
+    }
+}
+
+fn pull_transforms(mut query: Query<(&ViewUrl, &mut Transform), With<PullTransform>>) {
+    for (view_url, mut transform) in &mut query {
+        // A game object deleted on the Defold side keeps its last transform.
+        if let Ok(pulled) = defold::get_go_transform(view_url.0) {
+            transform.set_if_neq(pulled);
+        }
+    }
+}
+
+/// Pushes `Transform` changes of entities with a [`ViewUrl`] to their game objects
+/// through the [`TransformBatch`], and pulls them back for entities with [`PullTransform`].
+pub struct DefoldSyncPlugin;
+impl Plugin for DefoldSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TransformBatch>()
+            .add_systems(PreUpdate, pull_transforms)
+            .add_systems(
+                PostUpdate,
+                (sync_transforms, flush_transform_batch)
+                    .chain()
+                    .after(DefoldViewSet),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::CStr;
+
+    use bevy_app::Update;
+    use bevy_math::{Quat, Vec3};
+    use no_std_strings::ztr64;
+
+    use super::*;
+    use crate::defold_cpp_interface::{GO_TRANSFORM_SCALE, HashedOrStringUrl, HashedUrl};
+
+    fn options(channels: SyncChannels) -> TransformSync {
+        TransformSync {
+            channels,
+            translation_threshold: 0.1,
+            rotation_threshold: 0.1,
+            scale_threshold: 0.1,
+        }
+    }
+
+    #[test]
+    fn test_first_sync_always_sent() {
+        let transform = Transform::IDENTITY;
+        assert_eq!(
+            sync_action(&options(SyncChannels::All), None, &transform),
+            SyncAction::All
+        );
+        assert_eq!(
+            sync_action(&options(SyncChannels::RotationOnly), None, &transform),
+            SyncAction::Rotation
+        );
+    }
+
+    #[test]
+    fn test_change_below_threshold_skipped() {
+        let last = Transform::IDENTITY;
+        let current = Transform::from_translation(Vec3::new(0.05, 0.0, 0.0))
+            .with_rotation(Quat::from_rotation_z(0.05))
+            .with_scale(Vec3::splat(1.05));
+
+        assert_eq!(
+            sync_action(&options(SyncChannels::All), Some(&last), &current),
+            SyncAction::Skip
+        );
+    }
+
+    #[test]
+    fn test_any_channel_above_threshold_sends_all() {
+        let last = Transform::IDENTITY;
+        let current = Transform::from_scale(Vec3::splat(2.0));
+
+        assert_eq!(
+            sync_action(&options(SyncChannels::All), Some(&last), &current),
+            SyncAction::All
+        );
+    }
+
+    #[test]
+    fn test_single_channel_ignores_other_channels() {
+        let last = Transform::IDENTITY;
+        let moved = Transform::from_translation(Vec3::new(5.0, 0.0, 0.0));
+        let rotated = Transform::from_rotation(Quat::from_rotation_z(1.0));
+
+        assert_eq!(
+            sync_action(&options(SyncChannels::RotationOnly), Some(&last), &moved),
+            SyncAction::Skip
+        );
+        assert_eq!(
+            sync_action(
+                &options(SyncChannels::TranslationOnly),
+                Some(&last),
+                &rotated
+            ),
+            SyncAction::Skip
+        );
+        assert_eq!(
+            sync_action(&options(SyncChannels::TranslationOnly), Some(&last), &moved),
+            SyncAction::Translation
+        );
+        assert_eq!(
+            sync_action(&options(SyncChannels::RotationOnly), Some(&last), &rotated),
+            SyncAction::Rotation
+        );
+    }
+
+    #[test]
+    fn test_batch_entries_keep_hashed_and_string_urls() {
+        let hashed = HashedUrl {
+            socket: 1,
+            path: 2,
+            fragment: 0,
+        };
+        let mut batch = TransformBatch::default();
+        batch.set(
+            URL::with_hashed(ztr64::create("main:/a"), hashed),
+            Transform::IDENTITY,
+            GO_TRANSFORM_ALL,
+        );
+        batch.set(
+            URL::new(ztr64::create("main:/b")),
+            Transform::from_scale(Vec3::splat(2.0)),
+            GO_TRANSFORM_SCALE,
+        );
+
+        let entries: Vec<GoTransformBatchEntry> = batch.entries().collect();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, HashedOrStringUrl::Hashed(hashed));
+        assert_eq!(entries[0].channels, GO_TRANSFORM_ALL);
+        match entries[1].url {
+            HashedOrStringUrl::String(url) => {
+                let url = unsafe { CStr::from_ptr(url as *const cty::c_char) };
+                assert_eq!(url.to_str(), Ok("main:/b"));
+            }
+            other => panic!("unexpected url {:?}", other),
+        }
+        assert_eq!(entries[1].transform.scale, [2.0, 2.0, 2.0]);
+        assert_eq!(entries[1].channels, GO_TRANSFORM_SCALE);
+    }
+
+    #[test]
+    fn test_sync_queues_changed_channels_in_batch() {
+        let mut app = App::new();
+        app.init_resource::<TransformBatch>()
+            .add_systems(Update, sync_transforms);
+        let url = URL::new(ztr64::create("main:/unit"));
+        let entity = app
+            .world_mut()
+            .spawn((
+                ViewUrl(url),
+                Transform::IDENTITY,
+                TransformSync {
+                    channels: SyncChannels::RotationOnly,
+                    ..TransformSync::default()
+                },
+            ))
+            .id();
+        app.world_mut()
+            .spawn((ViewUrl(url), Transform::IDENTITY, PullTransform));
+
+        app.update();
+        app.world_mut()
+            .entity_mut(entity)
+            .get_mut::<Transform>()
+            .unwrap()
+            .rotate_z(1.0);
+        app.update();
+
+        let batch = app.world().resource::<TransformBatch>();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.updates[0].1, GO_TRANSFORM_ROTATION);
+        assert_eq!(batch.updates[1].1, GO_TRANSFORM_ROTATION);
+    }
+}
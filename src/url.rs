@@ -0,0 +1,358 @@
+use core::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use no_std_strings::ztr64;
+
+use crate::defold_cpp_interface::{HashedOrStringUrl, HashedUrl};
+
+/// Address of a Defold game object or component, `socket:/path#fragment`.
+/// Every part is optional: `#sprite` is a component of the current game object,
+/// `.` the current game object and `#` the current component.
+///
+/// URLs returned by the engine also carry their [`HashedUrl`], calls that have a
+/// hashed variant use it and the string is left for logging. `==` compares both the
+/// string and the hash, use [`URL::same_object`] to match an engine URL with a parsed one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct URL {
+    url: ztr64,
+    hashed: Option<HashedUrl>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UrlError {
+    Empty,
+    /// The URL is longer than [`URL::MAX_LEN`] bytes.
+    TooLong,
+    /// A URL handed over by the engine is not UTF-8.
+    InvalidUtf8,
+    InvalidSocket,
+    InvalidPath,
+    InvalidFragment,
+}
+
+struct UrlParts<'a> {
+    socket: Option<&'a str>,
+    path: &'a str,
+    fragment: Option<&'a str>,
+}
+
+fn is_url_char(c: char) -> bool {
+    !c.is_whitespace() && !c.is_control()
+}
+
+fn validate_socket(socket: &str) -> Result<(), UrlError> {
+    if socket.is_empty() || !socket.chars().all(|c| is_url_char(c) && !"/:#".contains(c)) {
+        return Err(UrlError::InvalidSocket);
+    }
+    Ok(())
+}
+
+/// Paths after a socket are absolute, `main:go` is rejected while `main:/go` and
+/// `main:` are fine.
+fn validate_path(path: &str, has_socket: bool) -> Result<(), UrlError> {
+    let absolute_or_empty = path.is_empty() || path.starts_with('/');
+    if (has_socket && !absolute_or_empty)
+        || path.contains("//")
+        || !path.chars().all(|c| is_url_char(c) && !":#".contains(c))
+    {
+        return Err(UrlError::InvalidPath);
+    }
+    Ok(())
+}
+
+fn validate_fragment(fragment: &str) -> Result<(), UrlError> {
+    if !fragment
+        .chars()
+        .all(|c| is_url_char(c) && !"/:#".contains(c))
+    {
+        return Err(UrlError::InvalidFragment);
+    }
+    Ok(())
+}
+
+fn split_url(url: &str) -> UrlParts<'_> {
+    let (rest, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    let (socket, path) = match rest.split_once(':') {
+        Some((socket, path)) => (Some(socket), path),
+        None => (None, rest),
+    };
+    UrlParts {
+        socket,
+        path,
+        fragment,
+    }
+}
+
+fn validate_parts(parts: &UrlParts) -> Result<(), UrlError> {
+    if parts.socket.is_none() && parts.path.is_empty() && parts.fragment.is_none() {
+        return Err(UrlError::Empty);
+    }
+    if let Some(socket) = parts.socket {
+        validate_socket(socket)?;
+    }
+    validate_path(parts.path, parts.socket.is_some())?;
+    if let Some(fragment) = parts.fragment {
+        validate_fragment(fragment)?;
+    }
+    Ok(())
+}
+
+impl URL {
+    /// Longest URL that fits, the buffer keeps one byte for the null terminator.
+    pub const MAX_LEN: usize = 63;
+
+    /// Wraps a URL produced by the engine as is, without validating it.
+    pub fn new(url: ztr64) -> Self {
+        Self { url, hashed: None }
+    }
+
+    pub(crate) fn with_hashed(url: ztr64, hashed: HashedUrl) -> Self {
+        Self {
+            url,
+            hashed: Some(hashed),
+        }
+    }
+
+    /// URL reported by the engine, overflowing ones are rejected rather than cut off.
+    pub(crate) fn from_engine(url: &[u8], hashed: HashedUrl) -> Result<Self, UrlError> {
+        let url = core::str::from_utf8(url).map_err(|_| UrlError::InvalidUtf8)?;
+        ztr64::try_make(url)
+            .map(|url| Self::with_hashed(url, hashed))
+            .map_err(|_| UrlError::TooLong)
+    }
+
+    pub fn parse(url: &str) -> Result<Self, UrlError> {
+        validate_parts(&split_url(url))?;
+        ztr64::try_make(url)
+            .map(Self::new)
+            .map_err(|_| UrlError::TooLong)
+    }
+
+    /// Builds `socket:path#fragment` from its parts, leaving out the missing ones.
+    pub fn from_parts(
+        socket: Option<&str>,
+        path: &str,
+        fragment: Option<&str>,
+    ) -> Result<Self, UrlError> {
+        validate_parts(&UrlParts {
+            socket,
+            path,
+            fragment,
+        })?;
+
+        let mut url = ztr64::new();
+        let written = socket
+            .map_or(Ok(()), |socket| write!(url, "{}:", socket))
+            .and_then(|_| url.write_str(path))
+            .and_then(|_| fragment.map_or(Ok(()), |fragment| write!(url, "#{}", fragment)));
+        written.map_err(|_| UrlError::TooLong)?;
+        Ok(Self::new(url))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.url.to_str()
+    }
+
+    /// Null terminated string form passed to the C++ side.
+    pub(crate) fn as_ptr(&self) -> *const u8 {
+        self.url.as_ptr()
+    }
+
+    pub fn hashed(&self) -> Option<HashedUrl> {
+        self.hashed
+    }
+
+    /// Hashed form when known, else a pointer to the string that lives as long as `self`.
+    pub(crate) fn as_cpp(&self) -> HashedOrStringUrl {
+        match self.hashed {
+            Some(hashed_url) => HashedOrStringUrl::Hashed(hashed_url),
+            None => HashedOrStringUrl::String(self.as_ptr()),
+        }
+    }
+
+    pub fn socket(&self) -> Option<&str> {
+        split_url(self.as_str()).socket
+    }
+
+    /// Path of the game object, empty when the URL only names a socket or a fragment.
+    pub fn path(&self) -> &str {
+        split_url(self.as_str()).path
+    }
+
+    /// Component name, `Some("")` for the `#` shorthand of the current component.
+    pub fn fragment(&self) -> Option<&str> {
+        split_url(self.as_str()).fragment
+    }
+
+    /// Whether both URLs address the same object: by hash when both have one,
+    /// otherwise by string. Not transitive across hashed and plain URLs, so it is
+    /// not `==`.
+    pub fn same_object(&self, other: &URL) -> bool {
+        match (self.hashed, other.hashed) {
+            (Some(hashed), Some(other_hashed)) => hashed == other_hashed,
+            _ => self.url == other.url,
+        }
+    }
+
+    /// Relative URLs are resolved by Defold against the sender.
+    pub fn is_relative(&self) -> bool {
+        self.socket().is_none()
+    }
+}
+
+impl FromStr for URL {
+    type Err = UrlError;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        Self::parse(url)
+    }
+}
+
+impl fmt::Display for URL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for URL {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URL({:?})", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_url() {
+        let url = URL::parse("main:/units/go#script").unwrap();
+
+        assert_eq!(url.socket(), Some("main"));
+        assert_eq!(url.path(), "/units/go");
+        assert_eq!(url.fragment(), Some("script"));
+        assert!(!url.is_relative());
+        assert_eq!(url.as_str(), "main:/units/go#script");
+    }
+
+    #[test]
+    fn test_parse_relative_urls() {
+        let current_object = URL::parse(".").unwrap();
+        assert_eq!(current_object.path(), ".");
+        assert_eq!(current_object.fragment(), None);
+
+        let current_component = URL::parse("#").unwrap();
+        assert_eq!(current_component.path(), "");
+        assert_eq!(current_component.fragment(), Some(""));
+
+        let sprite = URL::parse("#sprite").unwrap();
+        assert!(sprite.is_relative());
+        assert_eq!(sprite.fragment(), Some("sprite"));
+
+        assert_eq!(URL::parse("enemy").unwrap().path(), "enemy");
+        assert_eq!(URL::parse("main:").unwrap().socket(), Some("main"));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_urls() {
+        assert_eq!(URL::parse(""), Err(UrlError::Empty));
+        assert_eq!(URL::parse(":/go"), Err(UrlError::InvalidSocket));
+        assert_eq!(URL::parse("main:go"), Err(UrlError::InvalidPath));
+        assert_eq!(URL::parse("main:/go:x"), Err(UrlError::InvalidPath));
+        assert_eq!(URL::parse("/my go"), Err(UrlError::InvalidPath));
+        assert_eq!(URL::parse("/go#a#b"), Err(UrlError::InvalidFragment));
+        assert_eq!(URL::parse("/go#a/b"), Err(UrlError::InvalidFragment));
+    }
+
+    #[test]
+    fn test_long_url_reports_overflow() {
+        let path = String::from("/") + &"a".repeat(URL::MAX_LEN - 1);
+        assert!(URL::parse(&path).is_ok());
+
+        let too_long = path.clone() + "b";
+        assert_eq!(URL::parse(&too_long), Err(UrlError::TooLong));
+        assert_eq!(
+            URL::from_parts(Some("main"), &path, None),
+            Err(UrlError::TooLong)
+        );
+    }
+
+    #[test]
+    fn test_from_parts_round_trips() {
+        let url = URL::from_parts(Some("main"), "/go", Some("script")).unwrap();
+        assert_eq!(url, URL::parse("main:/go#script").unwrap());
+
+        assert_eq!(URL::from_parts(None, "", Some("")).unwrap().as_str(), "#");
+        assert_eq!(
+            URL::from_parts(Some("main"), "/go", Some("a/b")),
+            Err(UrlError::InvalidFragment)
+        );
+        assert_eq!(URL::from_parts(None, "", None), Err(UrlError::Empty));
+    }
+
+    #[test]
+    fn test_engine_url_keeps_hashed_form() {
+        let hashed = HashedUrl {
+            socket: 1,
+            path: 2,
+            fragment: 3,
+        };
+        let url = URL::with_hashed(ztr64::create("main:/go#script"), hashed);
+
+        assert_eq!(url.hashed(), Some(hashed));
+        assert_eq!(url.fragment(), Some("script"));
+        assert_eq!(URL::parse("main:/go#script").unwrap().hashed(), None);
+    }
+
+    #[test]
+    fn test_engine_url_overflow_reported() {
+        let hashed = HashedUrl {
+            socket: 1,
+            path: 2,
+            fragment: 0,
+        };
+        let path = String::from("/") + &"a".repeat(URL::MAX_LEN - 1);
+        assert!(URL::from_engine(path.as_bytes(), hashed).is_ok());
+
+        let too_long = path + "b";
+        assert_eq!(
+            URL::from_engine(too_long.as_bytes(), hashed),
+            Err(UrlError::TooLong)
+        );
+        assert_eq!(
+            URL::from_engine(b"/go\xff", hashed),
+            Err(UrlError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_same_object_ignores_missing_hash() {
+        let hashed = HashedUrl {
+            socket: 1,
+            path: 2,
+            fragment: 3,
+        };
+        let from_engine = URL::with_hashed(ztr64::create("main:/go#script"), hashed);
+        let parsed = URL::parse("main:/go#script").unwrap();
+
+        assert!(from_engine.same_object(&parsed));
+        assert_ne!(from_engine, parsed);
+        assert!(!from_engine.same_object(&URL::parse("main:/go#sprite").unwrap()));
+
+        // Both hashed, the engine's form wins over the spelling.
+        let relative = URL::with_hashed(ztr64::create("#script"), hashed);
+        assert!(from_engine.same_object(&relative));
+        let other = HashedUrl {
+            fragment: 4,
+            ..hashed
+        };
+        assert!(
+            !from_engine.same_object(&URL::with_hashed(ztr64::create("main:/go#script"), other))
+        );
+    }
+}
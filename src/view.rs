@@ -0,0 +1,249 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::*;
+use bevy_transform::components::Transform;
+use serde::Serialize;
+
+use crate::{
+    defold::{self, CreateViewError, DeleteViewError, URL},
+    defold_cpp_interface::dmhash_t,
+    defold_warn,
+};
+
+/// Game object spawned from `factory` for the entity, deleted again with its
+/// children when the component or the entity is removed.
+#[derive(Component, Clone)]
+pub struct DefoldView {
+    pub factory: dmhash_t,
+    /// Properties passed to the factory, already serialized to JSON.
+    pub properties_json: String,
+}
+
+/// Restored views are created again by Defold on the next frame.
+#[cfg(feature = "snapshot")]
+impl crate::snapshot::SnapshotComponent for DefoldView {
+    const KEY: &'static str = "defold_view";
+    type Saved = (dmhash_t, String);
+
+    fn save(&self) -> Self::Saved {
+        (self.factory, self.properties_json.clone())
+    }
+
+    fn restore((factory, properties_json): Self::Saved) -> Option<Self> {
+        Some(Self {
+            factory,
+            properties_json,
+        })
+    }
+}
+
+/// URL of the game object created for a [`DefoldView`].
+#[derive(Component, Clone, Copy)]
+pub struct ViewUrl(pub URL);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CreateViewFailed {
+    pub entity: Entity,
+    pub error: CreateViewError,
+}
+
+/// Game objects created by the app and not deleted yet, all of them are deleted
+/// when the app is destroyed. Views of [`DefoldView`] entities are kept here because
+/// the `ViewUrl` component is already gone once the entity is despawned, other views
+/// are registered by creating them through [`LiveViews::create_view`].
+#[derive(Resource, Default)]
+pub struct LiveViews {
+    entity_views: BTreeMap<Entity, URL>,
+    tracked_views: Vec<URL>,
+}
+
+impl LiveViews {
+    /// Creates a game object from the factory and registers it.
+    pub fn create_view<T: Serialize>(
+        &mut self,
+        view_factory_id: dmhash_t,
+        transform_to_set: Transform,
+        create_view_data: T,
+    ) -> Result<URL, CreateViewError> {
+        let url = defold::create_view(view_factory_id, transform_to_set, create_view_data)?;
+        self.track(url);
+        Ok(url)
+    }
+
+    /// Registers a game object created outside of [`LiveViews::create_view`].
+    pub fn track(&mut self, url: URL) {
+        self.tracked_views.push(url);
+    }
+
+    /// Deletes a tracked view and stops tracking it.
+    /// A view Defold already deleted is only forgotten.
+    pub fn delete(&mut self, url: URL, recursive: bool) -> Result<(), DeleteViewError> {
+        self.untrack(url);
+        delete_existing_view(url, recursive)
+    }
+
+    pub(crate) fn untrack(&mut self, url: URL) {
+        self.tracked_views
+            .retain(|tracked_url| !tracked_url.same_object(&url));
+    }
+
+    /// Empties the registry, returning every view it held.
+    fn take_all(&mut self) -> Vec<URL> {
+        let entity_views = core::mem::take(&mut self.entity_views).into_values();
+        entity_views
+            .chain(core::mem::take(&mut self.tracked_views))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entity_views.len() + self.tracked_views.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Deletes the view, one Defold already deleted counts as deleted.
+fn delete_existing_view(url: URL, recursive: bool) -> Result<(), DeleteViewError> {
+    match defold::delete_view(url, recursive) {
+        Err(DeleteViewError::NotFound) => Ok(()),
+        result => result,
+    }
+}
+
+fn delete_view_or_warn(url: URL) {
+    if let Err(error) = delete_existing_view(url, true) {
+        defold_warn!("deleting view {} failed: {:?}", url, error);
+    }
+}
+
+/// Deletes every game object in [`LiveViews`], called when the app is destroyed.
+pub(crate) fn delete_live_views(world: &mut World) {
+    let Some(mut live_views) = world.get_resource_mut::<LiveViews>() else {
+        return;
+    };
+    for url in live_views.take_all() {
+        delete_view_or_warn(url);
+    }
+}
+
+fn spawn_views(
+    mut commands: Commands,
+    mut live_views: ResMut<LiveViews>,
+    mut failures: EventWriter<CreateViewFailed>,
+    query: Query<(Entity, &DefoldView, Option<&Transform>), Added<DefoldView>>,
+) {
+    for (entity, view, transform) in &query {
+        let transform = transform.copied().unwrap_or_default();
+        match defold::create_view_from_json(
+            view.factory,
+            transform,
+            view.properties_json.as_bytes(),
+        ) {
+            Ok(url) => {
+                live_views.entity_views.insert(entity, url);
+                commands.entity(entity).insert(ViewUrl(url));
+            }
+            Err(error) => {
+                failures.send(CreateViewFailed { entity, error });
+            }
+        }
+    }
+}
+
+fn despawn_views(
+    mut commands: Commands,
+    mut live_views: ResMut<LiveViews>,
+    mut removed: RemovedComponents<DefoldView>,
+) {
+    for entity in removed.read() {
+        if let Some(url) = live_views.entity_views.remove(&entity) {
+            delete_view_or_warn(url);
+        }
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<ViewUrl>();
+        }
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DefoldViewSet;
+
+pub struct DefoldViewPlugin;
+impl Plugin for DefoldViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CreateViewFailed>()
+            .init_resource::<LiveViews>()
+            .add_systems(
+                PostUpdate,
+                (despawn_views, spawn_views).chain().in_set(DefoldViewSet),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use no_std_strings::ztr64;
+
+    use super::*;
+    use crate::defold_cpp_interface::HashedUrl;
+
+    #[test]
+    fn test_take_all_empties_live_views() {
+        let mut app = App::new();
+        app.add_plugins(DefoldViewPlugin);
+        let entity = app.world_mut().spawn_empty().id();
+        let entity_url = URL::new(ztr64::create("main:/view1"));
+        let tracked_url = URL::new(ztr64::create("main:/view2"));
+        let mut live_views = app.world_mut().resource_mut::<LiveViews>();
+        live_views.entity_views.insert(entity, entity_url);
+        live_views.track(tracked_url);
+        assert_eq!(live_views.len(), 2);
+
+        assert_eq!(live_views.take_all(), [entity_url, tracked_url]);
+        assert!(live_views.is_empty());
+    }
+
+    #[test]
+    fn test_oversized_properties_reported() {
+        let mut app = App::new();
+        app.add_plugins(DefoldViewPlugin);
+        let properties_json = "x".repeat(defold::max_payload_len() + 1);
+        let entity = app
+            .world_mut()
+            .spawn(DefoldView {
+                factory: 1,
+                properties_json,
+            })
+            .id();
+
+        app.update();
+
+        let failures = app.world().resource::<Events<CreateViewFailed>>();
+        let failure = failures.iter_current_update_events().next().unwrap();
+        assert_eq!(failure.entity, entity);
+        assert_eq!(
+            failure.error,
+            CreateViewError::InvalidProperties(defold::PayloadError::TooLarge)
+        );
+        assert!(app.world().resource::<LiveViews>().is_empty());
+    }
+
+    #[test]
+    fn test_untrack_matches_engine_and_parsed_urls() {
+        let mut live_views = LiveViews::default();
+        let hashed = HashedUrl {
+            socket: 1,
+            path: 2,
+            fragment: 0,
+        };
+        live_views.track(URL::with_hashed(ztr64::create("main:/view"), hashed));
+        live_views.track(URL::new(ztr64::create("main:/other")));
+
+        live_views.untrack(URL::parse("main:/view").unwrap());
+
+        assert_eq!(live_views.take_all(), [URL::parse("main:/other").unwrap()]);
+    }
+}